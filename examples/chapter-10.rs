@@ -1,21 +1,17 @@
-use image::{Rgb, RgbImage};
 use ray_tracing_the_next_week::{
-    random_float, Camera, Color, HittableList, Point3, Vec3, OUTPUT_PATH,
-};
-use std::{
-    fs::File,
-    sync::{Arc, Mutex},
-    thread::JoinHandle,
+    AdaptiveSampling, Camera, CheckpointError, Color, Denoiser, ExrImage, ExrPixelType,
+    HittableList, Pcg32, Point3, RenderSettings, Renderer, ToneMap, Vec3, OUTPUT_PATH,
 };
+use std::sync::Arc;
 mod random_scene {
     use std::sync::Arc;
 
     use ray_tracing_the_next_week::{
-        random_float, random_float_with_range, CheckerTexture, Color, Dielectric, HittableList,
-        Lambertian, Material, Metal, MovingSphere, Point3, Sphere, Vec3,
+        CheckerTexture, Color, Dielectric, HittableList, Lambertian, Material, Metal, MovingSphere,
+        Pcg32, Point3, Sphere, Vec3,
     };
 
-    pub fn gen() -> HittableList {
+    pub fn gen(rng: &mut Pcg32) -> HittableList {
         let mut world = HittableList::new();
         let checker =
             CheckerTexture::from_colors((Color::new((0.2, 0.3, 0.1)), Color::new((0.9, 0.9, 0.9))));
@@ -27,19 +23,19 @@ mod random_scene {
         )));
         for a in -11..11 {
             for b in -11..11 {
-                let choose_mat = random_float();
+                let choose_mat = rng.random_float();
                 let center = Point3::new((
-                    a as f64 + 0.9 * random_float(),
+                    a as f64 + 0.9 * rng.random_float(),
                     0.2,
-                    b as f64 + 0.9 * random_float(),
+                    b as f64 + 0.9 * rng.random_float(),
                 ));
                 if (center - Point3::new((4., 0.2, 0.))).len() > 0.9 {
                     let sphere_mat: Arc<dyn Material>;
                     if choose_mat < 0.8 {
-                        let albedo = Color::random() * Color::random();
+                        let albedo = Color::random(rng) * Color::random(rng);
                         sphere_mat = Arc::new(Lambertian::new(albedo));
                         let center2 =
-                            center + Vec3::new((0., random_float_with_range(0., 0.5), 0.));
+                            center + Vec3::new((0., rng.random_float_with_range(0., 0.5), 0.));
                         world.add(Arc::new(MovingSphere::new(
                             (center, center2),
                             (0., 1.),
@@ -47,8 +43,8 @@ mod random_scene {
                             sphere_mat,
                         )));
                     } else if choose_mat < 0.95 {
-                        let albedo = Color::random_with_range(0.5, 1.);
-                        let fuzz = random_float_with_range(0., 0.5);
+                        let albedo = Color::random_with_range(0.5, 1., rng);
+                        let fuzz = rng.random_float_with_range(0., 0.5);
                         sphere_mat = Arc::new(Metal::new(albedo, fuzz));
                         world.add(Arc::new(Sphere::new(center, 0.2, sphere_mat)));
                     } else {
@@ -94,11 +90,13 @@ mod two_spheres {
 mod two_perlin_spheres {
     use std::sync::Arc;
 
-    use ray_tracing_the_next_week::{HittableList, Lambertian, NoiseTexture, Point3, Sphere};
+    use ray_tracing_the_next_week::{
+        HittableList, Lambertian, NoiseTexture, Pcg32, Point3, Sphere,
+    };
 
-    pub fn gen() -> HittableList {
+    pub fn gen(rng: &mut Pcg32) -> HittableList {
         let mut objects = HittableList::new();
-        let pertext = NoiseTexture::new(4.);
+        let pertext = NoiseTexture::new(4., rng);
         objects.add(Arc::new(Sphere::new(
             Point3::new((0., -1000., 0.)),
             1000.,
@@ -134,13 +132,14 @@ mod simple_light {
     use std::sync::Arc;
 
     use ray_tracing_the_next_week::{
-        DiffuseLight, HittableList, ImageTexture, Lambertian, NoiseTexture, Point3, Sphere,
+        DiffuseLight, HittableList, ImageTexture, Lambertian, NoiseTexture, Pcg32, Point3, Sphere,
         XYPlane, TEXTURE_PATH,
     };
 
-    pub fn gen() -> HittableList {
+    /// the scene and its lights.
+    pub fn gen(rng: &mut Pcg32) -> (HittableList, HittableList) {
         let mut objects = HittableList::new();
-        let pertext = NoiseTexture::new(4.);
+        let pertext = NoiseTexture::new(4., rng);
         objects.add(Arc::new(Sphere::new(
             Point3::new((0., -1000., 0.)),
             1000.,
@@ -154,19 +153,21 @@ mod simple_light {
         let earth_texture =
             ImageTexture::new((String::from(TEXTURE_PATH) + "earthmap.jpg").as_str());
         let difflight = Arc::new(DiffuseLight::new(Arc::new(earth_texture)));
-        objects.add(Arc::new(XYPlane::new(difflight, (3., 5.), (1., 3.), -2.)));
-        objects
+        let light = Arc::new(XYPlane::new(difflight, (3., 5.), (1., 3.), -2.));
+        objects.add(light.clone());
+        (objects, HittableList::new_with_first_value(light))
     }
 }
 mod cornell_box {
     use std::sync::Arc;
 
     use ray_tracing_the_next_week::{
-        Color, DiffuseLight, HittableList, Lambertian, Point3, RotateY, Translate, Vec3, XYPlane,
+        Color, DiffuseLight, HittableList, Lambertian, Mat4, Point3, Transform, Vec3, XYPlane,
         XZPlane, YZPlane,
     };
 
-    pub fn gen() -> HittableList {
+    /// the scene and its lights.
+    pub fn gen() -> (HittableList, HittableList) {
         let mut objects = HittableList::new();
         let red = Arc::new(Lambertian::new(Color::new((0.65, 0.05, 0.05))));
         let white = Arc::new(Lambertian::new(Color::new((0.73, 0.73, 0.73))));
@@ -174,12 +175,8 @@ mod cornell_box {
         let light = Arc::new(DiffuseLight::with_solid_color(Color::new((45., 45., 45.))));
         objects.add(Arc::new(YZPlane::new(green, (0., 555.), (0., 555.), 555.)));
         objects.add(Arc::new(YZPlane::new(red, (0., 555.), (0., 555.), 0.)));
-        objects.add(Arc::new(XZPlane::new(
-            light,
-            (213., 343.),
-            (227., 332.),
-            554.,
-        )));
+        let light = Arc::new(XZPlane::new(light, (213., 343.), (227., 332.), 554.));
+        objects.add(light.clone());
         objects.add(Arc::new(XZPlane::new(
             white.clone(),
             (0., 555.),
@@ -203,23 +200,26 @@ mod cornell_box {
             Point3::new((165., 165., 165.)),
             white,
         ));
-        let box2 = Arc::new(RotateY::new(box2, -18.));
-
-        let box2 = Arc::new(Translate::new(Vec3::new((330., 0., 165.)), box2));
+        let box2 = Arc::new(Transform::new(
+            box2,
+            Mat4::translation(Vec3::new((330., 0., 165.)))
+                * Mat4::rotation(Vec3::new((0., 1., 0.)), -18.),
+        ));
         objects.add(box2);
 
-        return objects;
+        (objects, HittableList::new_with_first_value(light))
     }
 }
 mod cornell_smoke {
     use std::sync::Arc;
 
     use ray_tracing_the_next_week::{
-        Color, ConstantMedium, DiffuseLight, HittableList, Lambertian, Point3, RotateY, Translate,
+        Color, ConstantMedium, DiffuseLight, HittableList, Lambertian, Mat4, Point3, Transform,
         Vec3, XYPlane, XZPlane, YZPlane,
     };
 
-    pub fn gen() -> HittableList {
+    /// the scene and its lights.
+    pub fn gen() -> (HittableList, HittableList) {
         let mut objects = HittableList::new();
         let red = Arc::new(Lambertian::new(Color::new((0.65, 0.05, 0.05))));
         let white = Arc::new(Lambertian::new(Color::new((0.73, 0.73, 0.73))));
@@ -227,12 +227,8 @@ mod cornell_smoke {
         let light = Arc::new(DiffuseLight::with_solid_color(Color::new((7., 7., 7.))));
         objects.add(Arc::new(YZPlane::new(green, (0., 555.), (0., 555.), 555.)));
         objects.add(Arc::new(YZPlane::new(red, (0., 555.), (0., 555.), 0.)));
-        objects.add(Arc::new(XZPlane::new(
-            light,
-            (113., 443.),
-            (127., 432.),
-            554.,
-        )));
+        let light = Arc::new(XZPlane::new(light, (113., 443.), (127., 432.), 554.));
+        objects.add(light.clone());
         objects.add(Arc::new(XZPlane::new(
             white.clone(),
             (0., 555.),
@@ -256,26 +252,30 @@ mod cornell_smoke {
             Point3::new((165., 165., 165.)),
             white,
         ));
-        let box2 = Arc::new(RotateY::new(box2, -18.));
-        let box2 = Arc::new(Translate::new(Vec3::new((130., 0., 65.)), box2));
+        let box2 = Arc::new(Transform::new(
+            box2,
+            Mat4::translation(Vec3::new((130., 0., 65.)))
+                * Mat4::rotation(Vec3::new((0., 1., 0.)), -18.),
+        ));
         objects.add(Arc::new(ConstantMedium::from_color(
             box2,
             Color::new((1., 1., 1.)),
             0.01,
         )));
-        objects
+        (objects, HittableList::new_with_first_value(light))
     }
 }
 mod final_scene {
     use std::sync::Arc;
 
     use ray_tracing_the_next_week::{
-        random_float_with_range, BVHNode, Color, ConstantMedium, Dielectric, DiffuseLight,
-        HittableList, ImageTexture, Lambertian, Metal, MovingSphere, NoiseTexture, Point3, RotateY,
-        Sphere, Translate, Vec3, XZPlane, TEXTURE_PATH,
+        BVHNode, Color, ConstantMedium, Dielectric, DiffuseLight, HittableList, ImageTexture,
+        Lambertian, Mat4, Metal, MovingSphere, NoiseTexture, Pcg32, Point3, Sphere, Transform,
+        Vec3, XZPlane, TEXTURE_PATH,
     };
 
-    pub fn gen() -> HittableList {
+    /// the scene and its lights.
+    pub fn gen(rng: &mut Pcg32) -> (HittableList, HittableList) {
         let mut boxes1 = HittableList::new();
         let ground = Arc::new(Lambertian::new(Color::new((0.48, 0.83, 0.53))));
         const BOXES_PER_SIDE: usize = 20;
@@ -290,7 +290,7 @@ mod final_scene {
                 z.0 = -1000. + j as f64 * w;
                 z.1 = z.0 + w;
                 y.0 = 0.;
-                y.1 = random_float_with_range(1., 101.);
+                y.1 = rng.random_float_with_range(1., 101.);
                 boxes1.add(Arc::new(ray_tracing_the_next_week::Box::new(
                     Point3::new((x.0, y.0, z.0)),
                     Point3::new((x.1, y.1, z.1)),
@@ -302,12 +302,8 @@ mod final_scene {
         objects.add(Arc::new(BVHNode::from_hittable_list(boxes1, (0., 1.))));
 
         let light = Arc::new(DiffuseLight::with_solid_color(Color::new((7., 7., 7.))));
-        objects.add(Arc::new(XZPlane::new(
-            light,
-            (123., 423.),
-            (147., 412.),
-            554.,
-        )));
+        let light = Arc::new(XZPlane::new(light, (123., 423.), (147., 412.), 554.));
+        objects.add(light.clone());
 
         let center = (
             Point3::new((400., 400., 400.)),
@@ -361,7 +357,7 @@ mod final_scene {
             100.,
             emat,
         )));
-        let pretext = Arc::new(NoiseTexture::new(0.1));
+        let pretext = Arc::new(NoiseTexture::new(0.1, rng));
         objects.add(Arc::new(Sphere::new(
             Point3::new((220., 280., 300.)),
             100.,
@@ -373,19 +369,17 @@ mod final_scene {
         let ns = 1000;
         for _ in 0..ns {
             boxes2.add(Arc::new(Sphere::new(
-                Point3::random_with_range(0., 165.),
+                Point3::random_with_range(0., 165., rng),
                 10.,
                 white.clone(),
             )));
         }
-        objects.add(Arc::new(Translate::new(
-            Vec3::new((-100., 270., 395.)),
-            Arc::new(RotateY::new(
-                Arc::new(BVHNode::from_hittable_list(boxes2, (0., 1.))),
-                15.,
-            )),
+        objects.add(Arc::new(Transform::new(
+            Arc::new(BVHNode::from_hittable_list(boxes2, (0., 1.))),
+            Mat4::translation(Vec3::new((-100., 270., 395.)))
+                * Mat4::rotation(Vec3::new((0., 1., 0.)), 15.),
         )));
-        objects
+        (objects, HittableList::new_with_first_value(light))
     }
 }
 fn read_input() -> u8 {
@@ -414,15 +408,22 @@ fn main() {
     let mut image_width: u32 = 400;
     let mut samples_per_pixel: usize = 100; //samples_per_pixel
     const MAX_DEPTH: usize = 50;
+    const SAMPLES_PER_PASS: usize = 10;
+    // first hit features are cheap, a few rays per pixel smooth their edges
+    const AOV_SAMPLES: usize = 16;
     let background: Color;
     let mut world = HittableList::new();
+    let mut lights = None;
     let (mut lookfrom, mut lookat) = (Point3::default(), Point3::default());
     let (mut vfov, mut aperture) = (40., 0.);
     eprintln!("which type you want to render?\n 1 means a random scene;\n 2 means two_spheres;\n 3 means two_perlin_spheres;\n 4 means a earth sphere;\n 5 means simple_light;\n 6 means cornell_box\n 7 means cornell_smoke\n 8 means final_scene");
     let r#type = read_input();
+    // scenes with random content come out the same on every run
+    let mut scene_rng = Pcg32::default();
+    let mut with_aovs = true;
     match r#type {
         1 => {
-            world = random_scene::gen();
+            world = random_scene::gen(&mut scene_rng);
             background = Color::new((0.7, 0.8, 1.));
             lookfrom = Point3::new((13., 2., 3.));
             lookat = Point3::new((0., 0., 0.));
@@ -437,7 +438,7 @@ fn main() {
             vfov = 20.;
         }
         3 => {
-            world = two_perlin_spheres::gen();
+            world = two_perlin_spheres::gen(&mut scene_rng);
             background = Color::new((0.7, 0.8, 1.));
             lookfrom = Point3::new((13., 2., 3.));
             lookat = Point3::new((0., 0., 0.));
@@ -451,7 +452,9 @@ fn main() {
             vfov = 20.;
         }
         5 => {
-            world = simple_light::gen();
+            let (scene, scene_lights) = simple_light::gen(&mut scene_rng);
+            world = scene;
+            lights = Some(scene_lights);
             samples_per_pixel = 400;
             background = Color::default();
             lookfrom = Point3::new((26., 3., 6.));
@@ -459,7 +462,9 @@ fn main() {
             vfov = 20.;
         }
         6 => {
-            world = cornell_box::gen();
+            let (scene, scene_lights) = cornell_box::gen();
+            world = scene;
+            lights = Some(scene_lights);
             aspect_ratio = 1.0;
             image_width = 320;
             samples_per_pixel = 200;
@@ -469,7 +474,9 @@ fn main() {
             vfov = 40.0;
         }
         7 => {
-            world = cornell_smoke::gen();
+            let (scene, scene_lights) = cornell_smoke::gen();
+            world = scene;
+            lights = Some(scene_lights);
             aspect_ratio = 1.0;
             image_width = 600;
             samples_per_pixel = 200;
@@ -479,10 +486,14 @@ fn main() {
             vfov = 40.0;
         }
         _ => {
-            world = final_scene::gen();
+            let (scene, scene_lights) = final_scene::gen(&mut scene_rng);
+            world = scene;
+            lights = Some(scene_lights);
             aspect_ratio = 1.0;
             image_width = 8000;
             samples_per_pixel = 50;
+            // at this size the aovs and the denoiser's buffers would take tens of gigabytes
+            with_aovs = false;
             background = Color::default();
             lookfrom = Point3::new((478., 278., -600.));
             lookat = Point3::new((278., 278., 0.));
@@ -503,51 +514,78 @@ fn main() {
         0.,
         1.,
     );
-    let mut img = RgbImage::new(image_width, image_height);
-    println!("P3\n{} {}\n255", image_width, image_height);
-    let mut j: i64 = (image_height - 1) as i64;
-    let mut workers = Vec::new();
-    const THREADS:usize = 200;
-    let counter = Arc::new(Mutex::new(image_height * THREADS as u32));
-    while j >= 0 {
-        for i in 0..THREADS {
-            let world = world.clone();
-            let counter = Arc::clone(&counter);
-            workers.push((
-                std::thread::spawn(move || {
-                    let mut counter = counter.lock().unwrap();
-                    eprintln!("{} tasks left to do", *counter);
-                    let mut pixels = Vec::new();
-                    for e in 0..(image_width / THREADS as u32) {
-                        let mut pixel_color = Color::default();
-                        for _ in 0..samples_per_pixel {
-                            let u = ((i + e as usize * THREADS ) as f64 + random_float()) / (image_width - 1) as f64;
-                            let v = (j as f64 + random_float()) / (image_height - 1) as f64;
-                            let r = cam.get_ray(u, v);
-                            pixel_color +=
-                                r.ray_color(&background, &world, MAX_DEPTH as isize)
-                        }
-                        pixels.push((e, pixel_color.to_rgb8(samples_per_pixel)));
-                    }
-                    *counter -= 1;
-                    pixels
-
-                }),
-                i,
-                (image_height - 1) as i64 - j,
-            ));
-        }
-        j -= 1;
+    let settings = RenderSettings {
+        image_width,
+        image_height,
+        samples_per_pixel,
+        max_depth: MAX_DEPTH as isize,
+        adaptive: Some(AdaptiveSampling::default()),
+        progress: Some(|left| eprintln!("{} tiles left to do", left)),
+        ..Default::default()
+    };
+    let mut renderer = Renderer::new(cam, Arc::new(world), background, settings);
+    if let Some(lights) = lights {
+        renderer = renderer.with_lights(Arc::new(lights));
     }
-    for worker in workers {
-        let pixels = worker.0.join().unwrap();
-        for pixel in pixels {
-            // eprintln!("{}, {}", pixel.0, worker.1);
-            img.put_pixel((pixel.0 * THREADS as u32) + worker.1 as u32, worker.2 as u32, pixel.1);
+    // File::create((String::from(OUTPUT_PATH) + "image-10.jpg").as_str()).unwrap();
+    // an interrupted render of the same scene picks up from its last checkpoint
+    let checkpoint = String::from(OUTPUT_PATH) + "checkpoint-10.bin";
+    let start = match renderer.load_checkpoint(&checkpoint) {
+        Ok(accumulator) => {
+            eprintln!("resuming from {}", checkpoint);
+            accumulator
         }
+        Err(CheckpointError::Io { .. }) => renderer.accumulator(),
+        Err(e) => {
+            eprintln!("starting over, {}", e);
+            renderer.accumulator()
+        }
+    };
+    // the image is rewritten after every pass, so a long render can be stopped once it
+    // looks good enough
+    let accumulator = renderer.resume_progressive(start, SAMPLES_PER_PASS, |pass, accumulator| {
+        eprintln!("pass {} done", pass);
+        let img = accumulator
+            .to_framebuffer()
+            .to_srgb_image(&ToneMap::default());
+        img.save((String::from(OUTPUT_PATH) + "image-10.jpg").as_str())
+            .unwrap();
+        renderer.save_checkpoint(accumulator, &checkpoint).unwrap();
+    });
+    std::fs::remove_file(&checkpoint).ok();
+    // the linear image with nothing clipped, for tone mapping elsewhere, and the first hit
+    // features next to it for compositing
+    let framebuffer = accumulator.to_framebuffer();
+    let mut exr =
+        ExrImage::new(framebuffer.width(), framebuffer.height()).with_layer("", &framebuffer);
+    if with_aovs {
+        let aovs = renderer.render_aovs(AOV_SAMPLES);
+        exr = exr.with_aovs(&aovs);
+        // low sample previews come out far less noisy with the aovs to guide a denoiser
+        Denoiser::default()
+            .denoise(&framebuffer, &accumulator.variances(), &aovs)
+            .to_srgb_image(&ToneMap::default())
+            .save((String::from(OUTPUT_PATH) + "denoised-10.jpg").as_str())
+            .unwrap();
+        aovs.normal_image()
+            .save((String::from(OUTPUT_PATH) + "normal-10.png").as_str())
+            .unwrap();
+        aovs.albedo_image()
+            .save((String::from(OUTPUT_PATH) + "albedo-10.png").as_str())
+            .unwrap();
+        aovs.depth_image()
+            .save((String::from(OUTPUT_PATH) + "depth-10.png").as_str())
+            .unwrap();
     }
-    // File::create((String::from(OUTPUT_PATH) + "image-10.jpg").as_str()).unwrap();
-    img.save((String::from(OUTPUT_PATH) + "image-10.jpg").as_str())
+    exr.write(
+        String::from(OUTPUT_PATH) + "image-10.exr",
+        ExrPixelType::Half,
+    )
+    .unwrap();
+    accumulator
+        .sample_map()
+        .to_luma_image()
+        .save((String::from(OUTPUT_PATH) + "samples-10.png").as_str())
         .unwrap();
     eprintln!("\n Done! \n");
 }
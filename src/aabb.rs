@@ -15,16 +15,34 @@ impl AABB {
     pub fn max(&self) -> Point3 {
         self.maximum
     }
+    pub fn centroid(&self) -> Point3 {
+        0.5 * (self.minimum + self.maximum)
+    }
+    pub fn surface_area(&self) -> f64 {
+        let d = self.maximum - self.minimum;
+        2. * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+    /// slab test, true when the ray is inside the box somewhere in `t_in`.
+    /// touching a face or an edge counts as a hit.
     pub fn hit(&self, ray: &Ray, t_in: (f64, f64)) -> bool {
+        let (mut t_min, mut t_max) = t_in;
         for a in 0..3 {
-            let mut t = (
-                ((self.min()[a] - ray.orig()[a]) / ray.direction()[a])
-                    .min((self.max()[a] - ray.orig()[a]) / ray.direction()[a]),
-                ((self.min()[a] - ray.orig()[a]) / ray.direction()[a])
-                    .max((self.max()[a] - ray.orig()[a]) / ray.direction()[a]),
-            );
-            t = (t.0.min(t_in.0), t.1.max(t_in.1));
-            if t.1 < t.0 {
+            let origin = ray.orig()[a];
+            let inv_d = ray.inv_direction()[a];
+            if inv_d.is_infinite() {
+                // parallel to the slab, and a ray starting on a face would give 0 * inf = nan
+                if origin < self.minimum[a] || origin > self.maximum[a] {
+                    return false;
+                }
+                continue;
+            }
+            let slab = [self.minimum[a], self.maximum[a]];
+            let sign = ray.sign()[a];
+            let t_near = (slab[sign] - origin) * inv_d;
+            let t_far = (slab[1 - sign] - origin) * inv_d;
+            t_min = t_min.max(t_near);
+            t_max = t_max.min(t_far);
+            if t_max < t_min {
                 return false;
             }
         }
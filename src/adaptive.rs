@@ -0,0 +1,119 @@
+use image::{GrayImage, Luma};
+
+use crate::Color;
+
+// pixels darker than this count as this bright when judging their error, so near black
+// pixels don't chase a relative error they can never reach
+const MIN_LUMINANCE: f64 = 0.01;
+
+/// keeps sampling a pixel until the standard error of its mean luminance falls below
+/// `threshold` times the mean, with `samples_per_pixel` as the upper limit.
+#[derive(Clone, Copy, Debug)]
+pub struct AdaptiveSampling {
+    /// taken before the error is trusted, a few samples can agree by chance.
+    pub min_samples: usize,
+    pub threshold: f64,
+}
+
+impl Default for AdaptiveSampling {
+    fn default() -> Self {
+        Self {
+            min_samples: 16,
+            threshold: 0.01,
+        }
+    }
+}
+
+impl AdaptiveSampling {
+    pub fn converged(&self, estimate: &PixelEstimate) -> bool {
+        estimate.count() >= self.min_samples.max(2) && estimate.relative_error() < self.threshold
+    }
+}
+
+/// running mean of a pixel's samples, with the variance of their luminance kept by
+/// Welford's method.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PixelEstimate {
+    pub(crate) sum: Color,
+    pub(crate) count: usize,
+    pub(crate) mean: f64,
+    pub(crate) m2: f64,
+}
+
+impl PixelEstimate {
+    pub fn add(&mut self, sample: Color) {
+        self.sum += sample;
+        self.count += 1;
+        let luminance = luminance(&sample);
+        let delta = luminance - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (luminance - self.mean);
+    }
+    pub fn count(&self) -> usize {
+        self.count
+    }
+    pub fn color(&self) -> Color {
+        if self.count == 0 {
+            return Color::default();
+        }
+        self.sum / self.count as f64
+    }
+    /// sample variance of the luminance.
+    pub fn variance(&self) -> f64 {
+        if self.count < 2 {
+            return 0.;
+        }
+        self.m2 / (self.count - 1) as f64
+    }
+    /// standard error of the mean luminance relative to the mean itself.
+    pub fn relative_error(&self) -> f64 {
+        if self.count == 0 {
+            return f64::INFINITY;
+        }
+        (self.variance() / self.count as f64).sqrt() / self.mean.max(MIN_LUMINANCE)
+    }
+}
+
+pub fn luminance(c: &Color) -> f64 {
+    0.2126 * c.x() + 0.7152 * c.y() + 0.0722 * c.z()
+}
+
+/// how many samples each pixel took, row 0 is the top of the picture.
+#[derive(Clone, Debug)]
+pub struct SampleMap {
+    width: u32,
+    height: u32,
+    counts: Vec<usize>,
+}
+
+impl SampleMap {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            counts: vec![0; (width * height) as usize],
+        }
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    pub fn counts(&self) -> &[usize] {
+        &self.counts
+    }
+    pub fn get(&self, x: u32, y: u32) -> usize {
+        self.counts[(y * self.width + x) as usize]
+    }
+    pub fn put(&mut self, x: u32, y: u32, count: usize) {
+        self.counts[(y * self.width + x) as usize] = count;
+    }
+    /// white where a pixel took the most samples of the image, black for none.
+    pub fn to_luma_image(&self) -> GrayImage {
+        let max = self.counts.iter().copied().max().unwrap_or(0).max(1) as f64;
+        GrayImage::from_fn(self.width, self.height, |x, y| {
+            Luma([(self.get(x, y) as f64 / max * 255.).round() as u8])
+        })
+    }
+}
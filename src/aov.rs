@@ -0,0 +1,216 @@
+use std::{collections::HashMap, sync::Arc};
+
+use image::{GrayImage, Luma, Rgb, RgbImage};
+
+use crate::{
+    mix_bits, Color, ExrImage, Framebuffer, HitRecord, Point3, Ray, ToneMap, ToneMapOperator, Vec3,
+};
+
+/// what the camera rays of one pixel hit first, summed up until `Aovs::from_pixels`
+/// averages it.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct PixelAovs {
+    samples: usize,
+    hits: usize,
+    normal: Vec3,
+    position: Point3,
+    uv: (f64, f64),
+    depth: f64,
+    albedo: Color,
+    // taken from the first ray that hits anything
+    object_id: u32,
+    material: Option<usize>,
+}
+
+impl PixelAovs {
+    pub(crate) fn add(&mut self, ray: &Ray, rec: Option<&HitRecord>) {
+        self.samples += 1;
+        let rec = match rec {
+            Some(rec) => rec,
+            None => return,
+        };
+        if self.hits == 0 {
+            self.object_id = rec.object_id;
+            // materials have no name, their address tells them apart until renumbering
+            self.material = rec
+                .mat_ptr
+                .as_ref()
+                .map(|mat| Arc::as_ptr(mat) as *const () as usize);
+        }
+        self.hits += 1;
+        self.normal += rec.normal;
+        self.position += rec.p;
+        self.uv = (self.uv.0 + rec.u, self.uv.1 + rec.v);
+        self.depth += rec.t * ray.direction().len();
+        if let Some(mat) = &rec.mat_ptr {
+            self.albedo += mat.albedo(rec);
+        }
+    }
+}
+
+/// first hit arbitrary output variables of a render, row 0 is the top of the picture.
+/// the features are averaged over the rays of a pixel that hit something, and the alpha
+/// of each framebuffer is the fraction that did.
+#[derive(Clone, Debug)]
+pub struct Aovs {
+    width: u32,
+    height: u32,
+    normal: Framebuffer,
+    position: Framebuffer,
+    uv: Framebuffer,
+    albedo: Framebuffer,
+    depth: Vec<f64>,
+    object_ids: Vec<u32>,
+    material_ids: Vec<u32>,
+}
+
+impl Aovs {
+    /// `pixels` go row by row from the top. materials are numbered from 1 in the order
+    /// they first show up, so the ids stay the same from one run to the next.
+    pub(crate) fn from_pixels(width: u32, height: u32, pixels: &[PixelAovs]) -> Self {
+        let mut aovs = Self {
+            width,
+            height,
+            normal: Framebuffer::new(width, height),
+            position: Framebuffer::new(width, height),
+            uv: Framebuffer::new(width, height),
+            albedo: Framebuffer::new(width, height),
+            depth: vec![f64::INFINITY; (width * height) as usize],
+            object_ids: vec![0; (width * height) as usize],
+            material_ids: vec![0; (width * height) as usize],
+        };
+        let mut materials = HashMap::new();
+        for (i, pixel) in pixels.iter().enumerate() {
+            let (x, y) = (i as u32 % width, i as u32 / width);
+            let coverage = pixel.hits as f64 / pixel.samples.max(1) as f64;
+            for framebuffer in [
+                &mut aovs.normal,
+                &mut aovs.position,
+                &mut aovs.uv,
+                &mut aovs.albedo,
+            ] {
+                framebuffer.put_alpha(x, y, coverage);
+            }
+            if pixel.hits == 0 {
+                continue;
+            }
+            let hits = pixel.hits as f64;
+            aovs.normal.put_pixel(x, y, pixel.normal / hits);
+            aovs.position.put_pixel(x, y, pixel.position / hits);
+            aovs.uv
+                .put_pixel(x, y, Color::new((pixel.uv.0 / hits, pixel.uv.1 / hits, 0.)));
+            aovs.albedo.put_pixel(x, y, pixel.albedo / hits);
+            aovs.depth[i] = pixel.depth / hits;
+            aovs.object_ids[i] = pixel.object_id;
+            if let Some(material) = pixel.material {
+                let next = materials.len() as u32 + 1;
+                aovs.material_ids[i] = *materials.entry(material).or_insert(next);
+            }
+        }
+        aovs
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    /// world space shading normals, facing the camera.
+    pub fn normal(&self) -> &Framebuffer {
+        &self.normal
+    }
+    /// world space hit points.
+    pub fn position(&self) -> &Framebuffer {
+        &self.position
+    }
+    /// texture coordinates in red and green.
+    pub fn uv(&self) -> &Framebuffer {
+        &self.uv
+    }
+    pub fn albedo(&self) -> &Framebuffer {
+        &self.albedo
+    }
+    /// `depth` of every pixel, row by row from the top.
+    pub fn depths(&self) -> &[f64] {
+        &self.depth
+    }
+    /// distance from the camera to the hit, infinite where nothing was hit.
+    pub fn depth(&self, x: u32, y: u32) -> f64 {
+        self.depth[(y * self.width + x) as usize]
+    }
+    /// the `Tagged` id, 0 for no object or an untagged one.
+    pub fn object_id(&self, x: u32, y: u32) -> u32 {
+        self.object_ids[(y * self.width + x) as usize]
+    }
+    /// 0 where nothing was hit.
+    pub fn material_id(&self, x: u32, y: u32) -> u32 {
+        self.material_ids[(y * self.width + x) as usize]
+    }
+    /// normals mapped from [-1, 1] to [0, 1].
+    pub fn normal_image(&self) -> RgbImage {
+        self.normal
+            .map(|n| {
+                let n = if n.len() > 0. { n.unit_vector() } else { *n };
+                (n + Color::new((1., 1., 1.))) / 2.
+            })
+            .to_rgb_image()
+    }
+    /// white right in front of the camera fading to black at the farthest hit.
+    pub fn depth_image(&self) -> GrayImage {
+        let max = self
+            .depth
+            .iter()
+            .copied()
+            .filter(|d| d.is_finite())
+            .fold(0., f64::max);
+        GrayImage::from_fn(self.width, self.height, |x, y| {
+            let depth = self.depth(x, y);
+            if !depth.is_finite() || max <= 0. {
+                return Luma([0]);
+            }
+            Luma([((1. - depth / max) * 255.).round() as u8])
+        })
+    }
+    pub fn albedo_image(&self) -> RgbImage {
+        self.albedo
+            .to_srgb_image(&ToneMap::new(ToneMapOperator::Clamp))
+    }
+    pub fn uv_image(&self) -> RgbImage {
+        self.uv.to_rgb_image()
+    }
+    /// a random looking color per id, black for 0.
+    pub fn object_id_image(&self) -> RgbImage {
+        id_image(self.width, self.height, &self.object_ids)
+    }
+    pub fn material_id_image(&self) -> RgbImage {
+        id_image(self.width, self.height, &self.material_ids)
+    }
+}
+
+fn id_image(width: u32, height: u32, ids: &[u32]) -> RgbImage {
+    RgbImage::from_fn(width, height, |x, y| {
+        let id = ids[(y * width + x) as usize];
+        if id == 0 {
+            return Rgb([0; 3]);
+        }
+        let bits = mix_bits(id as u64);
+        Rgb([bits as u8, (bits >> 8) as u8, (bits >> 16) as u8])
+    })
+}
+
+impl ExrImage {
+    /// adds the layers `normal`, `position`, `uv` and `albedo`, depth as `Z` and the ids as
+    /// `objectId` and `materialId`. ids are only exact up to 2048 in half floats.
+    pub fn with_aovs(self, aovs: &Aovs) -> Self {
+        let uv = |f: fn(&Color) -> f64| aovs.uv.pixels().iter().map(|c| f(c) as f32).collect();
+        let ids = |ids: &[u32]| ids.iter().map(|&id| id as f32).collect();
+        self.with_layer("normal", &aovs.normal)
+            .with_layer("position", &aovs.position)
+            .with_channel("uv.U", uv(Color::x))
+            .with_channel("uv.V", uv(Color::y))
+            .with_layer("albedo", &aovs.albedo)
+            .with_channel("Z", aovs.depth.iter().map(|&d| d as f32).collect())
+            .with_channel("objectId", ids(&aovs.object_ids))
+            .with_channel("materialId", ids(&aovs.material_ids))
+    }
+}
@@ -1,97 +1,428 @@
-use crate::{random_integer_with_range, surrounding_box, HitRecord, Hittable, HittableList, AABB};
-use std::{cmp::Ordering, sync::Arc};
+use crate::{
+    surrounding_box, HitRecord, Hittable, HittableList, Point3, Ray, Vec3, AABB, INFINITY,
+    NEG_INFINITY,
+};
+use std::{cmp::Ordering, fmt, sync::Arc, thread};
 
+/// bvh over hittables, the nodes live in one array and each leaf holds a few objects.
+/// `refit` and `rebuild` need it unshared, so update it before it goes into the world's
+/// `Arc` or through `Arc::get_mut`.
 pub struct BVHNode {
-    pub(crate) left: Option<Arc<dyn Hittable>>,
-    pub(crate) right: Option<Arc<dyn Hittable>>,
-    pub(crate) r#box: AABB,
-}
-#[inline]
-fn compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>, axis: usize) -> Ordering {
-    let mut boxs = (AABB::default(), AABB::default());
-    if !a.as_ref().bounding_box((0., 0.), &mut boxs.0)
-        || !b.as_ref().bounding_box((0., 0.), &mut boxs.1)
-    {
-        eprintln!("No bounding box in bvh_node constructor.\n");
-    }
-    if boxs.0.min()[axis] == boxs.1.min()[axis] {
-        return Ordering::Equal;
-    } else if boxs.0.min()[axis] < boxs.1.min()[axis] {
-        return Ordering::Less;
-    } else {
-        return Ordering::Greater;
-    }
+    pub(crate) objects: Vec<Arc<dyn Hittable>>,
+    pub(crate) bvh: FlatBVH,
+    // `sah_cost` right after the last full build
+    pub(crate) built_cost: f64,
+    // kept for `rebuild`
+    pub(crate) split_method: SplitMethod,
+    pub(crate) threads: usize,
 }
 impl Hittable for BVHNode {
     fn hit(&self, ray: &crate::Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        if !self.r#box.hit(ray, (t_min, t_max)) {
-            return None;
-        }
-        let hit_left = match self.left.clone() {
-            Some(left) => left.as_ref().hit(ray, t_min, t_max),
-            None => None,
-        };
-        let hit_right = match self.right.clone() {
-            Some(right) => right.as_ref().hit(ray, t_min, t_max),
-            None => None,
-        };
-        if hit_left.is_some() {
-            return hit_left;
-        }
-        if hit_right.is_some() {
-            return hit_right;
-        }
-        None
+        self.bvh.hit(ray, t_min, t_max, |object, closest_so_far| {
+            self.objects[object].hit(ray, t_min, closest_so_far)
+        })
     }
 
     fn bounding_box(&self, _time: (f64, f64), output_box: &mut AABB) -> bool {
-        *output_box = self.r#box;
-        true
+        match self.bvh.bounding_box() {
+            Some(r#box) => {
+                *output_box = r#box;
+                true
+            }
+            None => false,
+        }
+    }
+}
+impl fmt::Debug for BVHNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BVHNode")
+            .field("objects", &self.objects.len())
+            .field("bvh", &self.bvh)
+            .finish()
     }
 }
+/// how a bvh decides where to split the primitives of a node.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SplitMethod {
+    /// binned surface area heuristic along the axis the centroids spread the most over.
+    #[default]
+    SurfaceAreaHeuristic,
+    /// half of the primitives on each side, sorted by box minimum along the axis the
+    /// minima spread the most over.
+    Median,
+}
+
 impl BVHNode {
     pub fn from_hittable_list(list: HittableList, time: (f64, f64)) -> Self {
         Self::from_objects(list.objects, time)
     }
     pub fn from_objects(raw_objects: Vec<Arc<dyn Hittable>>, time: (f64, f64)) -> Self {
-        let mut objects = raw_objects;
-        let axis = random_integer_with_range(0, 2);
-        let object_span = objects.len();
-        let (left, right): (Arc<dyn Hittable>, Arc<dyn Hittable>);
-        match object_span {
-            1 => {
-                left = objects[0].clone();
-                right = objects[0].clone();
-            }
-            2 => {
-                if compare(&objects[0], &objects[1], axis as usize) == Ordering::Less {
-                    left = objects[0].clone();
-                    right = objects[1].clone();
-                } else {
-                    left = objects[1].clone();
-                    right = objects[0].clone();
-                }
-            }
-            _ => {
-                objects.sort_by(|a, b| compare(a, b, axis as usize));
-                let mid = object_span / 2;
-                let mut left_objects = objects;
-                let right_objects = left_objects.split_off(mid);
-                left = Arc::new(BVHNode::from_objects(left_objects, time));
-                right = Arc::new(BVHNode::from_objects(right_objects, time));
+        Self::with_split_method(raw_objects, time, SplitMethod::default(), 0)
+    }
+    /// builds on up to `threads` threads, 0 means one per available core. the tree is the
+    /// same whatever the thread count.
+    pub fn with_split_method(
+        objects: Vec<Arc<dyn Hittable>>,
+        time: (f64, f64),
+        split_method: SplitMethod,
+        threads: usize,
+    ) -> Self {
+        let boxes = object_boxes(&objects, time);
+        let bvh = FlatBVH::with_split_method(&boxes, split_method, threads);
+        Self {
+            built_cost: bvh.sah_cost(),
+            bvh,
+            objects,
+            split_method,
+            threads,
+        }
+    }
+    /// recomputes the node bounds for the objects over `time`, keeping the tree as it is.
+    /// much cheaper than building again, but the tree gets worse the further things move
+    /// from where they were, see `cost_ratio`. only this level is refit, a `BVHNode` among
+    /// the objects or the faces of a `TriangleMesh` keep the bounds they were built with.
+    pub fn refit(&mut self, time: (f64, f64)) {
+        self.bvh.refit(&object_boxes(&self.objects, time));
+    }
+    /// builds the tree again over the same objects for `time`, with the split method and
+    /// threads it was first built with. nested bvhs are left alone here too.
+    pub fn rebuild(&mut self, time: (f64, f64)) {
+        let boxes = object_boxes(&self.objects, time);
+        self.bvh = FlatBVH::with_split_method(&boxes, self.split_method, self.threads);
+        self.built_cost = self.bvh.sah_cost();
+    }
+    /// expected cost of a ray traversing the tree, counting one per node visited and
+    /// one per object tested, for rays spread evenly over its bounding box.
+    pub fn sah_cost(&self) -> f64 {
+        self.bvh.sah_cost()
+    }
+    /// `sah_cost` now over `sah_cost` when the tree was built. refitting pushes it above 1,
+    /// and somewhere around 1.5 to 2 building again usually pays off.
+    pub fn cost_ratio(&self) -> f64 {
+        if self.built_cost <= 0. {
+            return 1.;
+        }
+        self.bvh.sah_cost() / self.built_cost
+    }
+}
+
+fn object_boxes(objects: &[Arc<dyn Hittable>], time: (f64, f64)) -> Vec<AABB> {
+    objects
+        .iter()
+        .map(|object| {
+            let mut output_box = AABB::default();
+            if !object.bounding_box(time, &mut output_box) {
+                eprintln!("No bounding box in bvh_node constructor.\n");
             }
+            output_box
+        })
+        .collect()
+}
+
+const SAH_BINS: usize = 12;
+
+fn largest_axis(extent: Vec3) -> usize {
+    if extent.x() > extent.y() && extent.x() > extent.z() {
+        0
+    } else if extent.y() > extent.z() {
+        1
+    } else {
+        2
+    }
+}
+
+fn centroid_bounds(indices: &[usize], centroids: &[Point3]) -> (Point3, Point3) {
+    indices.iter().fold(
+        (Point3::triple(INFINITY), Point3::triple(NEG_INFINITY)),
+        |(low, high), &i| (low.min(&centroids[i]), high.max(&centroids[i])),
+    )
+}
+
+/// puts the first half of `indices` by centroid along `axis` in front of the rest,
+/// returns where the second half starts.
+fn partition_median(indices: &mut [usize], centroids: &[Point3], axis: usize) -> usize {
+    let mid = indices.len() / 2;
+    indices.select_nth_unstable_by(mid, |&a, &b| {
+        centroids[a][axis]
+            .partial_cmp(&centroids[b][axis])
+            .unwrap_or(Ordering::Equal)
+    });
+    mid
+}
+
+/// sorts the centroids into equal width bins along the axis they spread the most over,
+/// and splits between the two bins where surface area times primitive count summed
+/// over both sides is lowest. returns where the right side starts in `indices` and the axis.
+fn partition_sah(indices: &mut [usize], boxes: &[AABB], centroids: &[Point3]) -> (usize, usize) {
+    let (low, high) = centroid_bounds(indices, centroids);
+    let axis = largest_axis(high - low);
+    let extent = high[axis] - low[axis];
+    // every centroid in the same spot, no plane separates them
+    if extent <= 0. {
+        return (partition_median(indices, centroids, axis), axis);
+    }
+    let bin_of = |i: usize| {
+        let bin = (centroids[i][axis] - low[axis]) / extent * SAH_BINS as f64;
+        (bin as usize).min(SAH_BINS - 1)
+    };
+    let mut bins: [(Option<AABB>, usize); SAH_BINS] = [(None, 0); SAH_BINS];
+    for &i in indices.iter() {
+        let bin = &mut bins[bin_of(i)];
+        bin.0 = Some(bin.0.map_or(boxes[i], |b| surrounding_box((b, boxes[i]))));
+        bin.1 += 1;
+    }
+    let merge = |a: (Option<AABB>, usize), b: (Option<AABB>, usize)| match (a.0, b.0) {
+        (Some(x), Some(y)) => (Some(surrounding_box((x, y))), a.1 + b.1),
+        (x, y) => (x.or(y), a.1 + b.1),
+    };
+    let cost =
+        |side: (Option<AABB>, usize)| side.0.map_or(0., |b| b.surface_area()) * side.1 as f64;
+
+    // the cost right of every split plane, sweeping in from the far end
+    let mut right_costs = [0.; SAH_BINS];
+    let mut right = (None, 0);
+    for split in (1..SAH_BINS).rev() {
+        right = merge(right, bins[split]);
+        right_costs[split] = cost(right);
+    }
+    let mut best = (INFINITY, 1);
+    let mut left = (None, 0);
+    for split in 1..SAH_BINS {
+        left = merge(left, bins[split - 1]);
+        let split_cost = cost(left) + right_costs[split];
+        if split_cost < best.0 {
+            best = (split_cost, split);
         }
-        let mut boxs = (AABB::default(), AABB::default());
-        if !left.as_ref().bounding_box(time, &mut boxs.0)
-            || !right.as_ref().bounding_box(time, &mut boxs.1)
-        {
-            eprintln!("No bounding box in bvh_node constructor.\n");
+    }
+    let mut mid = 0;
+    for j in 0..indices.len() {
+        if bin_of(indices[j]) < best.1 {
+            indices.swap(mid, j);
+            mid += 1;
         }
-        let r#box = surrounding_box(boxs);
-        Self {
-            left: Some(left),
-            right: Some(right),
+    }
+    // the lowest and highest centroids land in the first and last bin, so this only
+    // happens when some of them aren't finite
+    if mid == 0 || mid == indices.len() {
+        return (partition_median(indices, centroids, axis), axis);
+    }
+    (mid, axis)
+}
+
+const MAX_LEAF_SIZE: usize = 4;
+// subtrees over fewer primitives than this aren't worth a thread of their own
+const PARALLEL_BUILD_THRESHOLD: usize = 4096;
+
+#[derive(Clone, Copy, Debug)]
+enum FlatNodeKind {
+    Leaf { first: usize, count: usize },
+    // the left child always follows its parent directly and the right one is `right`
+    // nodes after it, `axis` is the one the node was split on
+    Interior { right: usize, axis: usize },
+}
+
+#[derive(Clone, Copy, Debug)]
+struct FlatNode {
+    r#box: AABB,
+    kind: FlatNodeKind,
+}
+
+/// bvh over primitives that aren't hittables of their own (like the faces of a mesh),
+/// nodes sit in one array and every leaf covers a range of `indices`.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct FlatBVH {
+    nodes: Vec<FlatNode>,
+    indices: Vec<usize>,
+}
+
+/// what every subtree build needs to see, shared between the building threads.
+struct FlatBuilder<'a> {
+    boxes: &'a [AABB],
+    // the points primitives are sorted by
+    keys: Vec<Point3>,
+    split_method: SplitMethod,
+}
+
+impl FlatBuilder<'_> {
+    /// appends the subtree over `indices`, which start at `first` in the whole index list.
+    /// big subtrees hand their right half to another thread while `threads` allows, the
+    /// splits never depend on it so the tree comes out the same on any machine.
+    fn build(
+        &self,
+        indices: &mut [usize],
+        first: usize,
+        threads: usize,
+        nodes: &mut Vec<FlatNode>,
+    ) {
+        let node = nodes.len();
+        let r#box = indices
+            .iter()
+            .map(|&i| self.boxes[i])
+            .reduce(|a, b| surrounding_box((a, b)))
+            .unwrap();
+        let count = indices.len();
+        if count <= MAX_LEAF_SIZE {
+            nodes.push(FlatNode {
+                r#box,
+                kind: FlatNodeKind::Leaf { first, count },
+            });
+            return;
+        }
+        let (mid, axis) = match self.split_method {
+            SplitMethod::SurfaceAreaHeuristic => partition_sah(indices, self.boxes, &self.keys),
+            SplitMethod::Median => {
+                let (low, high) = centroid_bounds(indices, &self.keys);
+                let axis = largest_axis(high - low);
+                (partition_median(indices, &self.keys, axis), axis)
+            }
+        };
+        nodes.push(FlatNode {
             r#box,
+            kind: FlatNodeKind::Interior { right: 0, axis },
+        });
+        let (left, right) = indices.split_at_mut(mid);
+        if threads > 1 && count >= PARALLEL_BUILD_THRESHOLD {
+            let right_nodes = thread::scope(|scope| {
+                let right_nodes = scope.spawn(|| {
+                    let mut right_nodes = Vec::with_capacity(2 * right.len());
+                    self.build(right, first + mid, threads / 2, &mut right_nodes);
+                    right_nodes
+                });
+                self.build(left, first, threads - threads / 2, nodes);
+                right_nodes.join().unwrap()
+            });
+            nodes[node].kind = FlatNodeKind::Interior {
+                right: nodes.len() - node,
+                axis,
+            };
+            nodes.extend(right_nodes);
+        } else {
+            self.build(left, first, 1, nodes);
+            nodes[node].kind = FlatNodeKind::Interior {
+                right: nodes.len() - node,
+                axis,
+            };
+            self.build(right, first + mid, 1, nodes);
+        }
+    }
+}
+
+impl FlatBVH {
+    pub(crate) fn build(boxes: &[AABB]) -> Self {
+        Self::with_split_method(boxes, SplitMethod::default(), 0)
+    }
+    /// `threads` 0 means one per available core.
+    pub(crate) fn with_split_method(
+        boxes: &[AABB],
+        split_method: SplitMethod,
+        threads: usize,
+    ) -> Self {
+        let mut bvh = Self {
+            nodes: Vec::with_capacity(2 * boxes.len()),
+            indices: (0..boxes.len()).collect(),
+        };
+        if !boxes.is_empty() {
+            let builder = FlatBuilder {
+                boxes,
+                keys: match split_method {
+                    SplitMethod::SurfaceAreaHeuristic => boxes.iter().map(AABB::centroid).collect(),
+                    SplitMethod::Median => boxes.iter().map(AABB::min).collect(),
+                },
+                split_method,
+            };
+            let threads = match threads {
+                0 => thread::available_parallelism().map_or(1, |n| n.get()),
+                n => n,
+            };
+            builder.build(&mut bvh.indices, 0, threads, &mut bvh.nodes);
+        }
+        bvh
+    }
+    /// updates every node box bottom up from new primitive boxes, in the same order as
+    /// the ones the tree was built from.
+    pub(crate) fn refit(&mut self, boxes: &[AABB]) {
+        // children always come after their parent
+        for index in (0..self.nodes.len()).rev() {
+            let r#box = match self.nodes[index].kind {
+                FlatNodeKind::Leaf { first, count } => self.indices[first..first + count]
+                    .iter()
+                    .map(|&i| boxes[i])
+                    .reduce(|a, b| surrounding_box((a, b)))
+                    .unwrap(),
+                FlatNodeKind::Interior { right, .. } => {
+                    surrounding_box((self.nodes[index + 1].r#box, self.nodes[index + right].r#box))
+                }
+            };
+            self.nodes[index].r#box = r#box;
+        }
+    }
+    pub(crate) fn sah_cost(&self) -> f64 {
+        let root_area = match self.nodes.first() {
+            Some(root) => root.r#box.surface_area(),
+            None => return 0.,
+        };
+        if root_area <= 0. {
+            return self.indices.len() as f64;
+        }
+        self.nodes
+            .iter()
+            .map(|node| {
+                let cost = match node.kind {
+                    FlatNodeKind::Leaf { count, .. } => count as f64,
+                    FlatNodeKind::Interior { .. } => 1.,
+                };
+                cost * node.r#box.surface_area() / root_area
+            })
+            .sum()
+    }
+    pub(crate) fn bounding_box(&self) -> Option<AABB> {
+        self.nodes.first().map(|node| node.r#box)
+    }
+    /// closest hit among the primitives, `hit_primitive` gets a primitive index and
+    /// the closest distance found so far. children are visited nearest first, so the
+    /// far one is usually culled by the hits found in the near one.
+    pub(crate) fn hit<F>(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+        mut hit_primitive: F,
+    ) -> Option<HitRecord>
+    where
+        F: FnMut(usize, f64) -> Option<HitRecord>,
+    {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let mut closest_so_far = t_max;
+        let mut rec = None;
+        let mut stack = Vec::with_capacity(64);
+        stack.push(0);
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !node.r#box.hit(ray, (t_min, closest_so_far)) {
+                continue;
+            }
+            match node.kind {
+                FlatNodeKind::Leaf { first, count } => {
+                    for &primitive in &self.indices[first..first + count] {
+                        if let Some(temp_rec) = hit_primitive(primitive, closest_so_far) {
+                            closest_so_far = temp_rec.t;
+                            rec = Some(temp_rec);
+                        }
+                    }
+                }
+                FlatNodeKind::Interior { right, axis } => {
+                    if ray.direction()[axis] < 0. {
+                        stack.push(index + 1);
+                        stack.push(index + right);
+                    } else {
+                        stack.push(index + right);
+                        stack.push(index + 1);
+                    }
+                }
+            }
         }
+        rec
     }
 }
@@ -1,4 +1,4 @@
-use crate::{degree_to_radians, random_float_with_range};
+use crate::{degree_to_radians, hash, Sampler};
 
 use super::{Point3, Ray, Vec3};
 #[derive(Clone, Copy)]
@@ -41,13 +41,31 @@ impl Default for Camera {
 }
 
 impl Camera {
-    pub fn get_ray(&self, s: f64, t: f64) -> Ray {
-        let rd = self.lens_radius * Vec3::random_in_unit_disk();
+    /// changes whenever the rays the camera shoots do.
+    pub(crate) fn hash(&self) -> u64 {
+        let vectors = [
+            self.origin,
+            self.lower_left_corner,
+            self.horizontal,
+            self.vertical,
+            self.u,
+            self.v,
+        ];
+        let mut values: Vec<u64> = vectors
+            .iter()
+            .flat_map(|v| [v.x(), v.y(), v.z()])
+            .map(f64::to_bits)
+            .collect();
+        values.extend([self.lens_radius, self.time.0, self.time.1].map(f64::to_bits));
+        hash(&values)
+    }
+    pub fn get_ray(&self, s: f64, t: f64, sampler: &mut dyn Sampler) -> Ray {
+        let rd = self.lens_radius * Vec3::random_in_unit_disk(sampler);
         let offset = self.u * rd + self.v * rd.y();
         Ray::new(
             self.origin + offset,
             self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin - offset,
-            random_float_with_range(self.time.0, self.time.1)
+            self.time.0 + sampler.get_1d() * (self.time.1 - self.time.0)
         )
     }
     pub fn new(
@@ -0,0 +1,177 @@
+use std::{
+    error, fmt, fs,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{Accumulator, Color, PixelEstimate};
+
+// file layout, all little endian: magic, settings hash, seed, width, height, then for
+// every pixel the color sum, sample count and the luminance mean and m2.
+const MAGIC: &[u8; 8] = b"rtckpt01";
+const HEADER_SIZE: usize = 8 + 8 + 8 + 4 + 4;
+const PIXEL_SIZE: usize = 6 * 8;
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Malformed {
+        path: PathBuf,
+        message: String,
+    },
+    /// the checkpoint belongs to a different scene or settings.
+    Mismatch {
+        path: PathBuf,
+        expected: u64,
+        found: u64,
+    },
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            CheckpointError::Malformed { path, message } => {
+                write!(f, "{}: {}", path.display(), message)
+            }
+            CheckpointError::Mismatch {
+                path,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{}: written for settings {:016x}, not {:016x}",
+                path.display(),
+                found,
+                expected
+            ),
+        }
+    }
+}
+
+impl error::Error for CheckpointError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            CheckpointError::Io { source, .. } => Some(source),
+            CheckpointError::Malformed { .. } | CheckpointError::Mismatch { .. } => None,
+        }
+    }
+}
+
+/// writes `accumulator` next to `path` first and then moves it over, so being killed
+/// halfway through leaves the previous checkpoint intact.
+///
+/// samplers derive their state from the seed, the pixel and its sample count, so those
+/// are all it takes to carry on with exactly the samples an uninterrupted render takes.
+pub fn save_checkpoint<P: AsRef<Path>>(
+    path: P,
+    hash: u64,
+    seed: u64,
+    accumulator: &Accumulator,
+) -> Result<(), CheckpointError> {
+    let path = path.as_ref();
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    let io_error = |source| CheckpointError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    let mut writer = BufWriter::new(fs::File::create(&partial).map_err(io_error)?);
+    let mut write = |bytes: &[u8]| writer.write_all(bytes).map_err(io_error);
+    write(MAGIC)?;
+    write(&hash.to_le_bytes())?;
+    write(&seed.to_le_bytes())?;
+    write(&accumulator.width().to_le_bytes())?;
+    write(&accumulator.height().to_le_bytes())?;
+    for estimate in accumulator.estimates() {
+        let sum = estimate.sum;
+        write(&sum.x().to_le_bytes())?;
+        write(&sum.y().to_le_bytes())?;
+        write(&sum.z().to_le_bytes())?;
+        write(&(estimate.count as u64).to_le_bytes())?;
+        write(&estimate.mean.to_le_bytes())?;
+        write(&estimate.m2.to_le_bytes())?;
+    }
+    writer
+        .into_inner()
+        .map_err(|e| io_error(e.into_error()))?
+        .sync_all()
+        .map_err(io_error)?;
+    fs::rename(&partial, path).map_err(io_error)
+}
+
+/// reads a checkpoint back, refusing one whose settings hash isn't `hash`.
+pub fn load_checkpoint<P: AsRef<Path>>(path: P, hash: u64) -> Result<Accumulator, CheckpointError> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|source| CheckpointError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let malformed = |message: &str| CheckpointError::Malformed {
+        path: path.to_path_buf(),
+        message: message.to_string(),
+    };
+    if bytes.len() < HEADER_SIZE || &bytes[..8] != MAGIC {
+        return Err(malformed("not a render checkpoint"));
+    }
+    let mut reader = Reader {
+        bytes: &bytes,
+        position: 8,
+    };
+    let found = reader.u64();
+    if found != hash {
+        return Err(CheckpointError::Mismatch {
+            path: path.to_path_buf(),
+            expected: hash,
+            found,
+        });
+    }
+    // the seed is part of the hash already, it's only there for anyone reading the file
+    let _seed = reader.u64();
+    let (width, height) = (reader.u32(), reader.u32());
+    if bytes.len() != HEADER_SIZE + width as usize * height as usize * PIXEL_SIZE {
+        return Err(malformed("pixel data doesn't match the image size"));
+    }
+    let mut accumulator = Accumulator::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let sum = Color::new((reader.f64(), reader.f64(), reader.f64()));
+            let estimate = PixelEstimate {
+                sum,
+                count: reader.u64() as usize,
+                mean: reader.f64(),
+                m2: reader.f64(),
+            };
+            accumulator.put(x, y, estimate);
+        }
+    }
+    Ok(accumulator)
+}
+
+// the sizes are checked up front, so reads can't run off the end
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> [u8; N] {
+        let mut out = [0; N];
+        out.copy_from_slice(&self.bytes[self.position..self.position + N]);
+        self.position += N;
+        out
+    }
+    fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.take())
+    }
+    fn u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.take())
+    }
+    fn f64(&mut self) -> f64 {
+        f64::from_le_bytes(self.take())
+    }
+}
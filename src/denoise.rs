@@ -0,0 +1,194 @@
+use std::thread;
+
+use crate::{luminance, Aovs, Color, Framebuffer};
+
+// keeps the color distance finite where neither pixel has any noise
+const EPSILON: f64 = 1e-10;
+
+/// non-local means filter guided by the first hit aovs. neighbors count for less the
+/// more their patch differs from the pixel's own patch beyond what the noise explains
+/// (Rousselle, Knaus and Zwicker, "Adaptive rendering with non-local means filtering"),
+/// and for nothing once the albedo, normal or depth tell them apart, which keeps edges
+/// and textures sharp.
+#[derive(Clone, Copy, Debug)]
+pub struct Denoiser {
+    /// neighbors up to this many pixels away are averaged in.
+    pub radius: u32,
+    /// pixels compare the squares of this radius around them rather than themselves alone.
+    pub patch_radius: u32,
+    /// how far apart colors may be, measured in their standard deviations.
+    pub color_strength: f64,
+    pub albedo_sigma: f64,
+    pub normal_sigma: f64,
+    /// relative to the distance of the nearer pixel.
+    pub depth_sigma: f64,
+    /// worker count, 0 means one worker per available core.
+    pub threads: usize,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self {
+            radius: 8,
+            patch_radius: 1,
+            color_strength: 1.,
+            albedo_sigma: 0.1,
+            normal_sigma: 0.3,
+            depth_sigma: 0.1,
+            threads: 0,
+        }
+    }
+}
+
+impl Denoiser {
+    /// `variance` is that of each pixel's mean luminance row by row from the top, like
+    /// `Accumulator::variances` gives. alpha is kept as it is.
+    pub fn denoise(&self, color: &Framebuffer, variance: &[f64], aovs: &Aovs) -> Framebuffer {
+        let (width, height) = (color.width(), color.height());
+        assert!(
+            aovs.width() == width && aovs.height() == height,
+            "aovs don't match the image size"
+        );
+        assert_eq!(variance.len(), (width * height) as usize);
+        let guides = Guides {
+            width: width as i64,
+            height: height as i64,
+            color,
+            luminances: color.pixels().iter().map(luminance).collect(),
+            variance,
+            aovs,
+        };
+        let threads = match self.threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+        // every pixel sees the same sums in the same order whatever band it's in, so the
+        // thread count doesn't change the result
+        let band_height = (height as usize).div_ceil(threads).max(1);
+        let filtered: Vec<Color> = thread::scope(|s| {
+            let workers: Vec<_> = (0..height as i64)
+                .step_by(band_height)
+                .map(|top| {
+                    let bottom = (top + band_height as i64).min(height as i64);
+                    let guides = &guides;
+                    s.spawn(move || self.denoise_band(guides, top, bottom))
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect()
+        });
+        let mut denoised = color.clone();
+        for y in 0..height {
+            for x in 0..width {
+                denoised.put_pixel(x, y, filtered[(y * width + x) as usize]);
+            }
+        }
+        denoised
+    }
+
+    // rows `top..bottom`, one neighbor offset at a time so each pixel distance is found
+    // once and then box filtered into the patch distances, first along the rows and then
+    // down the columns
+    fn denoise_band(&self, guides: &Guides, top: i64, bottom: i64) -> Vec<Color> {
+        let width = guides.width;
+        let (radius, patch_radius) = (self.radius as i64, self.patch_radius as i64);
+        let patch_size = ((2 * patch_radius + 1) * (2 * patch_radius + 1)) as f64;
+        let k2 = self.color_strength * self.color_strength;
+        // the band plus the rows its patches reach into
+        let rows = (bottom - top + 2 * patch_radius) as usize;
+        let mut distances = vec![0.; rows * width as usize];
+        let mut row_sums = vec![0.; rows * width as usize];
+        let mut sums = vec![Color::default(); ((bottom - top) * width) as usize];
+        let mut weights = vec![0.; ((bottom - top) * width) as usize];
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                for row in 0..rows {
+                    let y = top - patch_radius + row as i64;
+                    for x in 0..width {
+                        distances[row * width as usize + x as usize] =
+                            guides.distance(x, y, dx, dy, k2);
+                    }
+                }
+                for row in 0..rows {
+                    for x in 0..width {
+                        // off the sides the patch repeats the edge pixels, like the
+                        // clamped rows above and below
+                        let sum: f64 = (x - patch_radius..=x + patch_radius)
+                            .map(|px| {
+                                distances[row * width as usize + px.clamp(0, width - 1) as usize]
+                            })
+                            .sum();
+                        row_sums[row * width as usize + x as usize] = sum;
+                    }
+                }
+                for y in top..bottom {
+                    let row = (y - top) as usize;
+                    for x in 0..width {
+                        let distance: f64 = (0..=2 * patch_radius as usize)
+                            .map(|py| row_sums[(row + py) * width as usize + x as usize])
+                            .sum();
+                        let (p, q) = (guides.index(x, y), guides.index(x + dx, y + dy));
+                        let weight = (-(distance / patch_size).max(0.)).exp()
+                            * self.feature_weight(guides.aovs, p, q);
+                        let i = row * width as usize + x as usize;
+                        sums[i] += guides.color.pixels()[q] * weight;
+                        weights[i] += weight;
+                    }
+                }
+            }
+        }
+        // the pixel itself always has weight 1
+        sums.iter()
+            .zip(&weights)
+            .map(|(&sum, &weight)| sum / weight)
+            .collect()
+    }
+
+    fn feature_weight(&self, aovs: &Aovs, p: usize, q: usize) -> f64 {
+        let (depth_p, depth_q) = (aovs.depths()[p], aovs.depths()[q]);
+        // infinite depth is the background, which only goes with more background
+        let depth_distance = match (depth_p.is_finite(), depth_q.is_finite()) {
+            (true, true) => {
+                ((depth_p - depth_q) / (self.depth_sigma * depth_p.min(depth_q))).powi(2)
+            }
+            (false, false) => 0.,
+            _ => return 0.,
+        };
+        let albedo = aovs.albedo().pixels();
+        let normal = aovs.normal().pixels();
+        let albedo_distance =
+            (albedo[p] - albedo[q]).len_squared() / (self.albedo_sigma * self.albedo_sigma);
+        let normal_distance =
+            (normal[p] - normal[q]).len_squared() / (self.normal_sigma * self.normal_sigma);
+        (-(albedo_distance + normal_distance + depth_distance) / 2.).exp()
+    }
+}
+
+// what the filter looks at, pixels outside the image take the nearest edge pixel
+struct Guides<'a> {
+    width: i64,
+    height: i64,
+    color: &'a Framebuffer,
+    luminances: Vec<f64>,
+    variance: &'a [f64],
+    aovs: &'a Aovs,
+}
+
+impl Guides<'_> {
+    fn index(&self, x: i64, y: i64) -> usize {
+        let x = x.clamp(0, self.width - 1);
+        let y = y.clamp(0, self.height - 1);
+        (y * self.width + x) as usize
+    }
+    // how much the pixel and its neighbor at the offset differ beyond their noise
+    fn distance(&self, x: i64, y: i64, dx: i64, dy: i64, k2: f64) -> f64 {
+        let x = x.clamp(0, self.width - 1);
+        let y = y.clamp(0, self.height - 1);
+        let (p, q) = (self.index(x, y), self.index(x + dx, y + dy));
+        let (vp, vq) = (self.variance[p], self.variance[q]);
+        let difference = (self.luminances[p] - self.luminances[q]).powi(2);
+        (difference - (vp + vp.min(vq))) / (EPSILON + k2 * (vp + vq))
+    }
+}
@@ -0,0 +1,91 @@
+use image::{Rgb, RgbImage, Rgba, RgbaImage};
+
+use crate::Color;
+
+/// linear float rgba image, row 0 is the top of the picture. values keep their full
+/// range, turning them into something displayable is up to `tone_mapped`, `srgb_encoded`
+/// and the quantizing `to_rgb_image`.
+#[derive(Clone, Debug)]
+pub struct Framebuffer {
+    width: u32,
+    height: u32,
+    pixels: Vec<Color>,
+    alpha: Vec<f64>,
+}
+
+impl Framebuffer {
+    /// black and opaque.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Color::default(); (width * height) as usize],
+            alpha: vec![1.; (width * height) as usize],
+        }
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+    pub fn alpha(&self) -> &[f64] {
+        &self.alpha
+    }
+    pub fn get_pixel(&self, x: u32, y: u32) -> Color {
+        self.pixels[(y * self.width + x) as usize]
+    }
+    pub fn put_pixel(&mut self, x: u32, y: u32, color: Color) {
+        self.pixels[(y * self.width + x) as usize] = color;
+    }
+    pub fn get_alpha(&self, x: u32, y: u32) -> f64 {
+        self.alpha[(y * self.width + x) as usize]
+    }
+    pub fn put_alpha(&mut self, x: u32, y: u32, alpha: f64) {
+        self.alpha[(y * self.width + x) as usize] = alpha;
+    }
+    /// `f` applied to every color, alpha stays as it is.
+    pub fn map<F: Fn(&Color) -> Color>(&self, f: F) -> Self {
+        Self {
+            pixels: self.pixels.iter().map(f).collect(),
+            ..self.clone()
+        }
+    }
+    /// each channel raised to `1 / gamma`, negative values become 0.
+    pub fn gamma_corrected(&self, gamma: f64) -> Self {
+        self.map(|c| {
+            Color::new((
+                c.x().max(0.).powf(1. / gamma),
+                c.y().max(0.).powf(1. / gamma),
+                c.z().max(0.).powf(1. / gamma),
+            ))
+        })
+    }
+    /// clamps to [0, 1] and quantizes to 8 bits, the values are taken as already tone
+    /// mapped and encoded.
+    pub fn to_rgb_image(&self) -> RgbImage {
+        RgbImage::from_fn(self.width, self.height, |x, y| {
+            let c = self.get_pixel(x, y);
+            Rgb([quantize(c.x()), quantize(c.y()), quantize(c.z())])
+        })
+    }
+    /// `to_rgb_image` with alpha.
+    pub fn to_rgba_image(&self) -> RgbaImage {
+        RgbaImage::from_fn(self.width, self.height, |x, y| {
+            let c = self.get_pixel(x, y);
+            Rgba([
+                quantize(c.x()),
+                quantize(c.y()),
+                quantize(c.z()),
+                quantize(self.get_alpha(x, y)),
+            ])
+        })
+    }
+}
+
+fn quantize(value: f64) -> u8 {
+    (value.clamp(0., 0.999) * 256.) as u8
+}
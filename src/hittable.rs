@@ -1,4 +1,4 @@
-use crate::{AABB, Material, Point3, Vec3, surrounding_box};
+use crate::{AABB, Material, Point3, Sampler, Vec3, surrounding_box};
 use std::sync::Arc;
 pub mod sphere;
 pub use sphere::*;
@@ -8,6 +8,18 @@ pub mod r#box;
 pub use r#box::*;
 pub mod constant_medium;
 pub use constant_medium::*;
+pub mod triangle;
+pub use triangle::*;
+pub mod mesh;
+pub use mesh::*;
+pub mod transform;
+pub use transform::*;
+pub mod animated_transform;
+pub use animated_transform::*;
+pub mod instance;
+pub use instance::*;
+pub mod tagged;
+pub use tagged::*;
 use super::Ray;
 #[derive(Clone)]
 pub struct HitRecord {
@@ -18,6 +30,8 @@ pub struct HitRecord {
     pub(crate) v: f64,
     pub(crate) front_face: bool,
     pub(crate) mat_ptr: Option<Arc<dyn Material>>,
+    /// set by `Tagged`, 0 for untagged objects.
+    pub(crate) object_id: u32,
 }
 
 impl HitRecord {
@@ -38,6 +52,14 @@ impl HitRecord {
 pub trait Hittable: Send + Sync{
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
     fn bounding_box(&self, time: (f64, f64), output_box: &mut AABB) -> bool;
+    /// solid angle density of `random` seen from `origin`, 0 for objects that can't be sampled.
+    fn pdf_value(&self, _origin: &Point3, _v: &Vec3) -> f64 {
+        0.
+    }
+    /// a direction from `origin` towards a random point on the object.
+    fn random(&self, _origin: &Point3, _sampler: &mut dyn Sampler) -> Vec3 {
+        Vec3::new((1., 0., 0.))
+    }
 }
 
 
@@ -98,4 +120,25 @@ impl Hittable for HittableList {
         }
         true
     }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        if self.objects.is_empty() {
+            return 0.;
+        }
+        let weight = 1. / self.objects.len() as f64;
+        self.objects
+            .iter()
+            .map(|object| weight * object.pdf_value(origin, v))
+            .sum()
+    }
+
+    fn random(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        let count = self.objects.len();
+        // any direction does, `pdf_value` is 0 for all of them
+        if count == 0 {
+            return Vec3::new((1., 0., 0.));
+        }
+        let index = ((sampler.get_1d() * count as f64) as usize).min(count - 1);
+        self.objects[index].random(origin, sampler)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,177 @@
+use std::sync::Arc;
+
+use crate::{
+    surrounding_box, transformed_hit, HitRecord, Hittable, Mat4, Quaternion, Ray, Vec3, AABB,
+};
+
+/// pose of an animated object at `time`, applied as scale, then rotation, then translation.
+#[derive(Clone, Copy, Debug)]
+pub struct Keyframe {
+    pub(crate) time: f64,
+    pub(crate) translation: Vec3,
+    pub(crate) rotation: Quaternion,
+    pub(crate) scale: Vec3,
+}
+
+impl Keyframe {
+    /// panics when a component of `scale` is 0 or not finite, the pose couldn't be undone.
+    pub fn new(time: f64, translation: Vec3, rotation: Quaternion, scale: Vec3) -> Self {
+        Self::try_new(time, translation, rotation, scale).expect("keyframe scale is not invertible")
+    }
+    /// `None` when a component of `scale` is 0 or not finite.
+    pub fn try_new(
+        time: f64,
+        translation: Vec3,
+        rotation: Quaternion,
+        scale: Vec3,
+    ) -> Option<Self> {
+        let invertible = [scale.x(), scale.y(), scale.z()]
+            .iter()
+            .all(|s| s.is_finite() && *s != 0.);
+        if !invertible {
+            return None;
+        }
+        Some(Self {
+            time,
+            translation,
+            rotation: rotation.normalize(),
+            scale,
+        })
+    }
+    fn lerp(&self, other: &Self, time: f64) -> Self {
+        let t = (time - self.time) / (other.time - self.time);
+        Self {
+            time,
+            translation: (1. - t) * self.translation + t * other.translation,
+            rotation: self.rotation.slerp(&other.rotation, t),
+            scale: (1. - t) * self.scale + t * other.scale,
+        }
+    }
+    fn object_to_world(&self) -> Mat4 {
+        Mat4::from_trs(self.translation, self.rotation, self.scale)
+    }
+    fn world_to_object(&self) -> Mat4 {
+        let inverse_scale = Vec3::new((
+            1. / self.scale.x(),
+            1. / self.scale.y(),
+            1. / self.scale.z(),
+        ));
+        Mat4::scaling(inverse_scale)
+            * self.rotation.conjugate().to_mat4()
+            * Mat4::translation(-self.translation)
+    }
+}
+
+// largest rotation between two poses the bounding box is sampled at
+const MAX_BOX_STEP: f64 = 0.1;
+
+/// `ptr` moving through `keyframes`, interpolated at the time of each ray, so anything
+/// can get motion blur. before the first and after the last keyframe it holds still.
+/// animated lights are not supported: `pdf_value` and `random` get no time to pick a pose
+/// with, so they keep the defaults and the light is never sampled directly, only hit.
+pub struct AnimatedTransform {
+    pub(crate) ptr: Arc<dyn Hittable>,
+    pub(crate) keyframes: Vec<Keyframe>,
+}
+
+impl AnimatedTransform {
+    /// panics when a scale component changes sign from one keyframe to the next, it would
+    /// pass through 0 in between.
+    pub fn new(ptr: Arc<dyn Hittable>, mut keyframes: Vec<Keyframe>) -> Self {
+        assert!(
+            !keyframes.is_empty(),
+            "animation needs at least one keyframe"
+        );
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        for pair in keyframes.windows(2) {
+            let (a, b) = (pair[0].scale, pair[1].scale);
+            assert!(
+                a.x() * b.x() > 0. && a.y() * b.y() > 0. && a.z() * b.z() > 0.,
+                "scale changes sign between keyframes at {} and {}",
+                pair[0].time,
+                pair[1].time
+            );
+        }
+        Self { ptr, keyframes }
+    }
+    pub fn keyframe_at(&self, time: f64) -> Keyframe {
+        let after = self.keyframes.partition_point(|k| k.time <= time);
+        if after == 0 {
+            return Keyframe {
+                time,
+                ..self.keyframes[0]
+            };
+        }
+        if after == self.keyframes.len() {
+            return Keyframe {
+                time,
+                ..self.keyframes[after - 1]
+            };
+        }
+        self.keyframes[after - 1].lerp(&self.keyframes[after], time)
+    }
+}
+
+impl Hittable for AnimatedTransform {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let keyframe = self.keyframe_at(ray.time());
+        transformed_hit(
+            self.ptr.as_ref(),
+            &keyframe.object_to_world(),
+            &keyframe.world_to_object(),
+            ray,
+            t_min,
+            t_max,
+        )
+    }
+
+    fn bounding_box(&self, time: (f64, f64), output_box: &mut AABB) -> bool {
+        let mut child = AABB::default();
+        if !self.ptr.bounding_box(time, &mut child) {
+            return false;
+        }
+        // the motion only changes pace at keyframes, so each span between them is
+        // bounded on its own
+        let mut times = vec![time.0];
+        times.extend(
+            self.keyframes
+                .iter()
+                .map(|k| k.time)
+                .filter(|&t| t > time.0 && t < time.1),
+        );
+        times.push(time.1);
+        let corners = [child.min(), child.max()];
+        let mut r#box: Option<AABB> = None;
+        for span in times.windows(2) {
+            let (start, end) = (self.keyframe_at(span[0]), self.keyframe_at(span[1]));
+            // without rotation the box at the two ends is exact, translation and scale
+            // change linearly. rotation is sampled, and the corners can bulge out of the
+            // sampled boxes by about the sagitta of the arc between two samples.
+            let angle = start.rotation.angle_to(&end.rotation);
+            let steps = (angle / MAX_BOX_STEP).ceil().max(1.) as usize;
+            let scale = start
+                .scale
+                .max(&end.scale)
+                .max(&-start.scale.min(&end.scale));
+            let radius = (0..8)
+                .map(|i| {
+                    let corner = Vec3::new((
+                        corners[i & 1].x(),
+                        corners[(i >> 1) & 1].y(),
+                        corners[i >> 2].z(),
+                    ));
+                    (corner * scale).len()
+                })
+                .fold(0., f64::max);
+            let padding = Vec3::triple(radius * (1. - (0.5 * angle / steps as f64).cos()));
+            for step in 0..=steps {
+                let t = span[0] + (span[1] - span[0]) * step as f64 / steps as f64;
+                let sampled = self.keyframe_at(t).object_to_world().transform_box(&child);
+                let sampled = AABB::new(sampled.min() - padding, sampled.max() + padding);
+                r#box = Some(r#box.map_or(sampled, |b| surrounding_box((b, sampled))));
+            }
+        }
+        *output_box = r#box.unwrap();
+        true
+    }
+}
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use crate::{Color, HitRecord, Hittable, INFINITY, Isotropic, Material, NEG_INFINITY, Ray, Texture, Vec3, random_float};
+use crate::{Color, HitRecord, Hittable, INFINITY, Isotropic, Material, NEG_INFINITY, Ray, Texture, Vec3};
 
 pub struct ConstantMedium {
     pub(crate) boundary: Arc<dyn Hittable>,
@@ -10,8 +10,6 @@ pub struct ConstantMedium {
 
 impl Hittable for ConstantMedium {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        const ENABLE_DEBUG: bool = false;
-        let debuging: bool = ENABLE_DEBUG && random_float() < 0.00001;
         let rec1 = self.boundary.hit(ray, NEG_INFINITY, INFINITY);
         match rec1 {
             None => return None,
@@ -20,9 +18,6 @@ impl Hittable for ConstantMedium {
                 match rec2 {
                     None => return None,
                     Some(mut rec2) => {
-                        if debuging {
-                            eprintln!("t_min={},t_max={}", rec1.t, rec2.t);
-                        }
                         if rec1.t < t_min {
                             rec1.t = t_min;
                         }
@@ -37,19 +32,16 @@ impl Hittable for ConstantMedium {
                         }
                         let ray_len: f64 = ray.direction().len();
                         let distane_inside_boundary = (rec2.t - rec1.t) * ray_len;
-                        let hit_distance = self.neg_inv_density * random_float().ln();
+                        // `hit` gets no sampler, the integrator hands the free path's sample
+                        // over on the ray, fresh for every medium it meets
+                        let hit_distance =
+                            self.neg_inv_density * (1. - ray.take_medium_sample()).ln();
                         if hit_distance > distane_inside_boundary {
                             return None;
                         }
                         let mut rec = HitRecord::default();
                         rec.t = rec1.t + hit_distance / ray_len;
                         rec.p = ray.at(rec.t);
-                        if debuging {
-                            eprintln!(
-                                "hit_distance = {}\nrec.t = {}\nrec.p = {:?}",
-                                hit_distance, rec.t, rec.p
-                            );
-                        }
                         rec.normal = Vec3::new((1., 0., 0.));
                         rec.front_face = true;
                         rec.mat_ptr = Some(self.phase_function.clone());
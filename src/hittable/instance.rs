@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use crate::{HitRecord, Hittable, Mat4, Material, Point3, Ray, Sampler, Transform, Vec3, AABB};
+
+/// one placement of a shared bottom level structure (any hittable, usually a `BVHNode`
+/// or a `TriangleMesh`) under its own transform. the geometry is only stored once behind
+/// the `Arc`, and a `BVHNode` over the instances makes the top level.
+pub struct Instance {
+    transform: Transform,
+    mat_ptr: Option<Arc<dyn Material>>,
+}
+
+impl Instance {
+    /// panics when `object_to_world` is singular.
+    pub fn new(object: Arc<dyn Hittable>, object_to_world: Mat4) -> Self {
+        Self::try_new(object, object_to_world).expect("transform is not invertible")
+    }
+    /// `None` when `object_to_world` is singular.
+    pub fn try_new(object: Arc<dyn Hittable>, object_to_world: Mat4) -> Option<Self> {
+        Some(Self {
+            transform: Transform::try_new(object, object_to_world)?,
+            mat_ptr: None,
+        })
+    }
+    /// renders the instance with `mat_ptr` instead of the materials of the shared object.
+    pub fn with_material(mut self, mat_ptr: Arc<dyn Material>) -> Self {
+        self.mat_ptr = Some(mat_ptr);
+        self
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut rec = self.transform.hit(ray, t_min, t_max)?;
+        if let Some(mat_ptr) = &self.mat_ptr {
+            rec.mat_ptr = Some(mat_ptr.clone());
+        }
+        Some(rec)
+    }
+
+    fn bounding_box(&self, time: (f64, f64), output_box: &mut AABB) -> bool {
+        self.transform.bounding_box(time, output_box)
+    }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        self.transform.pdf_value(origin, v)
+    }
+
+    fn random(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.transform.random(origin, sampler)
+    }
+}
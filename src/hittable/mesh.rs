@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use crate::{
+    intersect_triangle, triangle_bounding_box, triangle_hit_record, FlatBVH, HitRecord, Hittable,
+    Material, Point3, Ray, Vec3, AABB,
+};
+
+/// indexed triangles sharing one set of vertex buffers and one material,
+/// hit through a bvh over its own faces.
+pub struct TriangleMesh {
+    positions: Vec<Point3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<(f64, f64)>,
+    indices: Vec<[usize; 3]>,
+    mat_ptr: Arc<dyn Material>,
+    bvh: FlatBVH,
+}
+
+impl TriangleMesh {
+    pub fn new(
+        positions: Vec<Point3>,
+        indices: Vec<[usize; 3]>,
+        mat_ptr: Arc<dyn Material>,
+    ) -> Self {
+        Self::with_attributes(positions, None, None, indices, mat_ptr)
+    }
+    /// `normals` and `uvs` are per vertex, so they need as many entries as `positions`.
+    pub fn with_attributes(
+        positions: Vec<Point3>,
+        normals: Option<Vec<Vec3>>,
+        uvs: Option<Vec<(f64, f64)>>,
+        indices: Vec<[usize; 3]>,
+        mat_ptr: Arc<dyn Material>,
+    ) -> Self {
+        let normals = normals.unwrap_or_default();
+        let uvs = uvs.unwrap_or_default();
+        assert!(
+            normals.is_empty() || normals.len() == positions.len(),
+            "mesh has {} normals for {} vertices",
+            normals.len(),
+            positions.len()
+        );
+        assert!(
+            uvs.is_empty() || uvs.len() == positions.len(),
+            "mesh has {} uvs for {} vertices",
+            uvs.len(),
+            positions.len()
+        );
+        assert!(
+            indices.iter().flatten().all(|&i| i < positions.len()),
+            "mesh face refers to a vertex out of range"
+        );
+        let boxes: Vec<AABB> = indices
+            .iter()
+            .map(|face| triangle_bounding_box(&face.map(|i| positions[i])))
+            .collect();
+        Self {
+            bvh: FlatBVH::build(&boxes),
+            positions,
+            normals,
+            uvs,
+            indices,
+            mat_ptr,
+        }
+    }
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len()
+    }
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+    pub fn material(&self) -> &Arc<dyn Material> {
+        &self.mat_ptr
+    }
+    fn hit_face(&self, face: usize, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let index = self.indices[face];
+        let vertices = index.map(|i| self.positions[i]);
+        let (t, barycentric) = intersect_triangle(ray, &vertices, t_min, t_max)?;
+        let normals = match self.normals.is_empty() {
+            true => None,
+            false => Some(index.map(|i| self.normals[i])),
+        };
+        let uvs = match self.uvs.is_empty() {
+            true => [(0., 0.), (1., 0.), (0., 1.)],
+            false => index.map(|i| self.uvs[i]),
+        };
+        Some(triangle_hit_record(
+            ray,
+            t,
+            barycentric,
+            &vertices,
+            normals.as_ref(),
+            &uvs,
+            self.mat_ptr.clone(),
+        ))
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.bvh.hit(ray, t_min, t_max, |face, closest_so_far| {
+            self.hit_face(face, ray, t_min, closest_so_far)
+        })
+    }
+
+    fn bounding_box(&self, _time: (f64, f64), output_box: &mut AABB) -> bool {
+        match self.bvh.bounding_box() {
+            Some(r#box) => {
+                *output_box = r#box;
+                true
+            }
+            None => false,
+        }
+    }
+}
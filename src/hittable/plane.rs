@@ -1,6 +1,8 @@
 use std::{cell::RefCell, rc::Rc, sync::Arc};
 
-use crate::{AABB, Color, HitRecord, Hittable, Lambertian, Material, Point3, Ray, Vec3};
+use crate::{
+    Color, HitRecord, Hittable, Lambertian, Material, Point3, Ray, Sampler, Vec3, AABB, INFINITY,
+};
 pub struct XYPlane {
     mp: Arc<dyn Material>,
     x: (f64, f64),
@@ -43,13 +45,33 @@ impl Hittable for XYPlane {
         );
         true
     }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        match self.hit(&Ray::new(*origin, *v, 0.), 0.001, INFINITY) {
+            Some(rec) => {
+                let area = (self.x.1 - self.x.0) * (self.y.1 - self.y.0);
+                let distance_squared = rec.t.powi(2) * v.len_squared();
+                let cosine = (v.dot(&rec.normal) / v.len()).abs();
+                distance_squared / (cosine * area)
+            }
+            None => 0.,
+        }
+    }
+
+    fn random(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        let (s, t) = sampler.get_2d();
+        let random_point = Point3::new((
+            self.x.0 + s * (self.x.1 - self.x.0),
+            self.y.0 + t * (self.y.1 - self.y.0),
+            self.k,
+        ));
+        random_point - *origin
+    }
 }
 impl Default for XYPlane {
     fn default() -> Self {
         Self {
-            mp: Arc::new(Lambertian::new(Color::new((
-                255., 255., 255.,
-            )))),
+            mp: Arc::new(Lambertian::new(Color::new((255., 255., 255.)))),
             x: (0., 0.),
             y: (0., 0.),
             k: 0.,
@@ -98,13 +120,33 @@ impl Hittable for XZPlane {
         );
         true
     }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        match self.hit(&Ray::new(*origin, *v, 0.), 0.001, INFINITY) {
+            Some(rec) => {
+                let area = (self.x.1 - self.x.0) * (self.z.1 - self.z.0);
+                let distance_squared = rec.t.powi(2) * v.len_squared();
+                let cosine = (v.dot(&rec.normal) / v.len()).abs();
+                distance_squared / (cosine * area)
+            }
+            None => 0.,
+        }
+    }
+
+    fn random(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        let (s, t) = sampler.get_2d();
+        let random_point = Point3::new((
+            self.x.0 + s * (self.x.1 - self.x.0),
+            self.k,
+            self.z.0 + t * (self.z.1 - self.z.0),
+        ));
+        random_point - *origin
+    }
 }
 impl Default for XZPlane {
     fn default() -> Self {
         Self {
-            mp: Arc::new(Lambertian::new(Color::new((
-                255., 255., 255.,
-            )))),
+            mp: Arc::new(Lambertian::new(Color::new((255., 255., 255.)))),
             x: (0., 0.),
             z: (0., 0.),
             k: 0.,
@@ -153,16 +195,36 @@ impl Hittable for YZPlane {
         );
         true
     }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        match self.hit(&Ray::new(*origin, *v, 0.), 0.001, INFINITY) {
+            Some(rec) => {
+                let area = (self.y.1 - self.y.0) * (self.z.1 - self.z.0);
+                let distance_squared = rec.t.powi(2) * v.len_squared();
+                let cosine = (v.dot(&rec.normal) / v.len()).abs();
+                distance_squared / (cosine * area)
+            }
+            None => 0.,
+        }
+    }
+
+    fn random(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        let (s, t) = sampler.get_2d();
+        let random_point = Point3::new((
+            self.k,
+            self.y.0 + s * (self.y.1 - self.y.0),
+            self.z.0 + t * (self.z.1 - self.z.0),
+        ));
+        random_point - *origin
+    }
 }
 impl Default for YZPlane {
     fn default() -> Self {
         Self {
-            mp: Arc::new(Lambertian::new(Color::new((
-                255., 255., 255.,
-            )))),
+            mp: Arc::new(Lambertian::new(Color::new((255., 255., 255.)))),
             y: (0., 0.),
             z: (0., 0.),
             k: 0.,
         }
     }
-}
\ No newline at end of file
+}
@@ -1,6 +1,9 @@
 use std::sync::Arc;
 
-use crate::{AABB, HitRecord, Hittable, Material, PI, Point3, Ray, Vec3, surrounding_box};
+use crate::{
+    surrounding_box, HitRecord, Hittable, Material, Point3, Ray, Sampler, Vec3, AABB, INFINITY,
+    ONB, PI,
+};
 
 pub struct Sphere {
     center: Point3,
@@ -45,6 +48,37 @@ impl Hittable for Sphere {
         );
         true
     }
+
+    // from inside or on the sphere there is no cone to sample, the light is left to
+    // material sampling there
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        let distance_squared = (self.center - *origin).len_squared();
+        if distance_squared <= self.radius.powi(2) {
+            return 0.;
+        }
+        if self
+            .hit(&Ray::new(*origin, *v, 0.), 0.001, INFINITY)
+            .is_none()
+        {
+            return 0.;
+        }
+        let cos_theta_max = (1. - self.radius.powi(2) / distance_squared).sqrt();
+        let solid_angle = 2. * PI * (1. - cos_theta_max);
+        1. / solid_angle
+    }
+
+    fn random(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        let direction = self.center - *origin;
+        if direction.len_squared() <= self.radius.powi(2) {
+            return Vec3::random_unit_vector(sampler);
+        }
+        let uvw = ONB::build_from_w(&direction);
+        uvw.local(&random_to_sphere(
+            self.radius,
+            direction.len_squared(),
+            sampler,
+        ))
+    }
 }
 
 impl Sphere {
@@ -62,6 +96,16 @@ impl Sphere {
     }
 }
 
+/// uniform direction inside the cone a sphere subtends, around +z.
+fn random_to_sphere(radius: f64, distance_squared: f64, sampler: &mut dyn Sampler) -> Vec3 {
+    let (r1, r2) = sampler.get_2d();
+    let z = 1. + r2 * ((1. - radius.powi(2) / distance_squared).sqrt() - 1.);
+    let phi = 2. * PI * r1;
+    let x = phi.cos() * (1. - z.powi(2)).sqrt();
+    let y = phi.sin() * (1. - z.powi(2)).sqrt();
+    Vec3::new((x, y, z))
+}
+
 pub struct MovingSphere {
     center: (Point3, Point3),
     time: (f64, f64),
@@ -129,4 +173,4 @@ impl Hittable for MovingSphere {
         *output_box = surrounding_box(b);
         true
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,37 @@
+use std::sync::Arc;
+
+use crate::{HitRecord, Hittable, Point3, Ray, Sampler, Vec3, AABB};
+
+/// `object` with every hit marked as `id` for the object id aov. an outer tag replaces
+/// the tags of anything nested inside, so a tagged group counts as one object.
+pub struct Tagged {
+    object: Arc<dyn Hittable>,
+    id: u32,
+}
+
+impl Tagged {
+    /// `id` should not be 0, that's what untagged objects get.
+    pub fn new(object: Arc<dyn Hittable>, id: u32) -> Self {
+        Self { object, id }
+    }
+}
+
+impl Hittable for Tagged {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut rec = self.object.hit(ray, t_min, t_max)?;
+        rec.object_id = self.id;
+        Some(rec)
+    }
+
+    fn bounding_box(&self, time: (f64, f64), output_box: &mut AABB) -> bool {
+        self.object.bounding_box(time, output_box)
+    }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        self.object.pdf_value(origin, v)
+    }
+
+    fn random(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        self.object.random(origin, sampler)
+    }
+}
@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use crate::{HitRecord, Hittable, Mat4, Point3, Ray, Sampler, Vec3, AABB};
+
+/// `ptr` moved by an affine transform, which may rotate around any axis, scale unevenly
+/// and shear. compose several with `Mat4` multiplication rather than nesting transforms.
+pub struct Transform {
+    pub(crate) ptr: Arc<dyn Hittable>,
+    pub(crate) object_to_world: Mat4,
+    pub(crate) world_to_object: Mat4,
+}
+
+impl Transform {
+    /// panics when `object_to_world` is singular, like a scale of 0 along some axis.
+    pub fn new(ptr: Arc<dyn Hittable>, object_to_world: Mat4) -> Self {
+        Self::try_new(ptr, object_to_world).expect("transform is not invertible")
+    }
+    /// `None` when `object_to_world` is singular.
+    pub fn try_new(ptr: Arc<dyn Hittable>, object_to_world: Mat4) -> Option<Self> {
+        let world_to_object = object_to_world.inverse()?;
+        Some(Self {
+            ptr,
+            object_to_world,
+            world_to_object,
+        })
+    }
+}
+
+impl Hittable for Transform {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        transformed_hit(
+            self.ptr.as_ref(),
+            &self.object_to_world,
+            &self.world_to_object,
+            ray,
+            t_min,
+            t_max,
+        )
+    }
+
+    fn bounding_box(&self, time: (f64, f64), output_box: &mut AABB) -> bool {
+        if !self.ptr.bounding_box(time, output_box) {
+            return false;
+        }
+        *output_box = self.object_to_world.transform_box(output_box);
+        true
+    }
+
+    fn pdf_value(&self, origin: &Point3, v: &Vec3) -> f64 {
+        let local = self.world_to_object.transform_vector(v);
+        let pdf = self
+            .ptr
+            .pdf_value(&self.world_to_object.transform_point(origin), &local);
+        // taking unit directions through the linear part stretches solid angles by
+        // |det| / |stretch of the direction|³, which is 1 for rotations and uniform scales
+        let stretch = local.len() / v.len();
+        pdf * self.world_to_object.determinant().abs() / stretch.powi(3)
+    }
+
+    fn random(&self, origin: &Point3, sampler: &mut dyn Sampler) -> Vec3 {
+        let direction = self
+            .ptr
+            .random(&self.world_to_object.transform_point(origin), sampler);
+        self.object_to_world.transform_vector(&direction)
+    }
+}
+
+/// hits `object` through the pair of matrices, giving the record in world space.
+pub(crate) fn transformed_hit(
+    object: &dyn Hittable,
+    object_to_world: &Mat4,
+    world_to_object: &Mat4,
+    ray: &Ray,
+    t_min: f64,
+    t_max: f64,
+) -> Option<HitRecord> {
+    // the direction keeps its scale, so `t` means the same in both spaces
+    let local = Ray::new(
+        world_to_object.transform_point(ray.orig()),
+        world_to_object.transform_vector(ray.direction()),
+        ray.time(),
+    )
+    .with_medium_sample(ray.medium_sample());
+    let rec = object.hit(&local, t_min, t_max);
+    ray.follow_medium_sample(&local);
+    let mut rec = rec?;
+    rec.p = object_to_world.transform_point(&rec.p);
+    // an affine map keeps the sign of normal·direction, so `front_face` still holds
+    rec.normal = world_to_object.transform_normal(&rec.normal).unit_vector();
+    Some(rec)
+}
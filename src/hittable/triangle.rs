@@ -0,0 +1,154 @@
+use std::sync::Arc;
+
+use crate::{HitRecord, Hittable, Material, Point3, Ray, Vec3, AABB};
+
+pub struct Triangle {
+    vertices: [Point3; 3],
+    normals: Option<[Vec3; 3]>,
+    uvs: [(f64, f64); 3],
+    mat_ptr: Arc<dyn Material>,
+}
+
+impl Triangle {
+    pub fn new(vertices: [Point3; 3], mat_ptr: Arc<dyn Material>) -> Self {
+        Self::with_attributes(vertices, None, None, mat_ptr)
+    }
+    /// without `normals` the triangle is flat shaded, without `uvs` the corners get
+    /// (0, 0), (1, 0) and (0, 1).
+    pub fn with_attributes(
+        vertices: [Point3; 3],
+        normals: Option<[Vec3; 3]>,
+        uvs: Option<[(f64, f64); 3]>,
+        mat_ptr: Arc<dyn Material>,
+    ) -> Self {
+        Self {
+            vertices,
+            normals,
+            uvs: uvs.unwrap_or([(0., 0.), (1., 0.), (0., 1.)]),
+            mat_ptr,
+        }
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (t, barycentric) = intersect_triangle(ray, &self.vertices, t_min, t_max)?;
+        Some(triangle_hit_record(
+            ray,
+            t,
+            barycentric,
+            &self.vertices,
+            self.normals.as_ref(),
+            &self.uvs,
+            self.mat_ptr.clone(),
+        ))
+    }
+
+    fn bounding_box(&self, _time: (f64, f64), output_box: &mut AABB) -> bool {
+        *output_box = triangle_bounding_box(&self.vertices);
+        true
+    }
+}
+
+/// watertight ray/triangle test (Woop, Benthin and Wald 2013), edges shared by two
+/// triangles never let a ray slip through. returns `t` and the barycentric weights.
+pub(crate) fn intersect_triangle(
+    ray: &Ray,
+    vertices: &[Point3; 3],
+    t_min: f64,
+    t_max: f64,
+) -> Option<(f64, [f64; 3])> {
+    let dir = *ray.direction();
+    // shear the triangle into a space where the ray runs along +z
+    let abs_dir = Vec3::new((dir.x().abs(), dir.y().abs(), dir.z().abs()));
+    let kz = if abs_dir.x() > abs_dir.y() {
+        if abs_dir.x() > abs_dir.z() {
+            0
+        } else {
+            2
+        }
+    } else if abs_dir.y() > abs_dir.z() {
+        1
+    } else {
+        2
+    };
+    let mut kx = (kz + 1) % 3;
+    let mut ky = (kx + 1) % 3;
+    if dir[kz] < 0. {
+        std::mem::swap(&mut kx, &mut ky);
+    }
+    let shear = (dir[kx] / dir[kz], dir[ky] / dir[kz], 1. / dir[kz]);
+
+    let a = vertices[0] - *ray.orig();
+    let b = vertices[1] - *ray.orig();
+    let c = vertices[2] - *ray.orig();
+    let (ax, ay) = (a[kx] - shear.0 * a[kz], a[ky] - shear.1 * a[kz]);
+    let (bx, by) = (b[kx] - shear.0 * b[kz], b[ky] - shear.1 * b[kz]);
+    let (cx, cy) = (c[kx] - shear.0 * c[kz], c[ky] - shear.1 * c[kz]);
+
+    let u = cx * by - cy * bx;
+    let v = ax * cy - ay * cx;
+    let w = bx * ay - by * ax;
+    if (u < 0. || v < 0. || w < 0.) && (u > 0. || v > 0. || w > 0.) {
+        return None;
+    }
+    let det = u + v + w;
+    if det == 0. {
+        return None;
+    }
+    let t_scaled = u * shear.2 * a[kz] + v * shear.2 * b[kz] + w * shear.2 * c[kz];
+    let t = t_scaled / det;
+    if !(t_min..=t_max).contains(&t) {
+        return None;
+    }
+    Some((t, [u / det, v / det, w / det]))
+}
+
+pub(crate) fn triangle_hit_record(
+    ray: &Ray,
+    t: f64,
+    barycentric: [f64; 3],
+    vertices: &[Point3; 3],
+    normals: Option<&[Vec3; 3]>,
+    uvs: &[(f64, f64); 3],
+    mat_ptr: Arc<dyn Material>,
+) -> HitRecord {
+    let [b0, b1, b2] = barycentric;
+    let mut rec = HitRecord {
+        t,
+        p: ray.at(t),
+        mat_ptr: Some(mat_ptr),
+        ..Default::default()
+    };
+    rec.set_uv((
+        b0 * uvs[0].0 + b1 * uvs[1].0 + b2 * uvs[2].0,
+        b0 * uvs[0].1 + b1 * uvs[1].1 + b2 * uvs[2].1,
+    ));
+    let mut geometric_normal = (vertices[1] - vertices[0])
+        .cross(&(vertices[2] - vertices[0]))
+        .unit_vector();
+    let shading_normal = normals.map(|n| (b0 * n[0] + b1 * n[1] + b2 * n[2]).unit_vector());
+    // vertex normals tell which side is outside whatever the winding is,
+    // but the side a ray comes from is still decided by the real surface.
+    if let Some(shading_normal) = shading_normal {
+        if geometric_normal.dot(&shading_normal) < 0. {
+            geometric_normal = -geometric_normal;
+        }
+    }
+    rec.set_face_normal(ray, geometric_normal);
+    if let Some(shading_normal) = shading_normal {
+        rec.normal = match rec.front_face {
+            true => shading_normal,
+            false => -shading_normal,
+        };
+    }
+    rec
+}
+
+pub(crate) fn triangle_bounding_box(vertices: &[Point3; 3]) -> AABB {
+    let padding = Vec3::triple(0.0001);
+    AABB::new(
+        vertices[0].min(&vertices[1]).min(&vertices[2]) - padding,
+        vertices[0].max(&vertices[1]).max(&vertices[2]) + padding,
+    )
+}
@@ -31,6 +31,14 @@ pub mod camera;
 pub use camera::*;
 pub mod material;
 pub use material::*;
+pub mod onb;
+pub use onb::*;
+pub mod rng;
+pub use rng::*;
+pub mod sampler;
+pub use sampler::*;
+pub mod matrix;
+pub use matrix::*;
 pub mod aabb;
 pub use aabb::*;
 pub mod bvh;
@@ -45,5 +53,24 @@ pub use texture::*;
 pub mod perlin;
 pub use perlin::*;
 
+pub mod framebuffer;
+pub use framebuffer::*;
+pub mod output;
+pub use output::*;
+pub mod tonemap;
+pub use tonemap::*;
+pub mod adaptive;
+pub use adaptive::*;
+pub mod aov;
+pub use aov::*;
+pub mod denoise;
+pub use denoise::*;
+pub mod renderer;
+pub use renderer::*;
+pub mod checkpoint;
+pub use checkpoint::*;
+pub mod loader;
+pub use loader::*;
+
 pub const TEXTURE_PATH: &'static str = "./assets/texture/";
 pub const OUTPUT_PATH: &'static str = "./outputs/";
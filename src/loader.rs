@@ -0,0 +1,68 @@
+use std::{error, fmt, io, path::PathBuf};
+
+pub mod obj;
+pub use obj::*;
+pub mod ply;
+pub use ply::*;
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// a texture named on `line` of the material library at `path` that couldn't be read.
+    Texture {
+        path: PathBuf,
+        line: usize,
+        texture: PathBuf,
+        source: image::ImageError,
+    },
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    /// problems that can't be pinned to a line, like a binary body ending early.
+    Malformed {
+        path: PathBuf,
+        message: String,
+    },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            LoadError::Texture {
+                path,
+                line,
+                texture,
+                source,
+            } => write!(
+                f,
+                "{}:{}: {}: {}",
+                path.display(),
+                line,
+                texture.display(),
+                source
+            ),
+            LoadError::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path.display(), line, message),
+            LoadError::Malformed { path, message } => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+impl error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            LoadError::Io { source, .. } => Some(source),
+            LoadError::Texture { source, .. } => Some(source),
+            LoadError::Parse { .. } | LoadError::Malformed { .. } => None,
+        }
+    }
+}
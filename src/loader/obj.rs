@@ -0,0 +1,355 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    Color, Dielectric, DiffuseLight, ImageTexture, Lambertian, LoadError, Material, Metal, Point3,
+    TriangleMesh, Vec3,
+};
+
+/// loads a wavefront obj file, giving one mesh per material it uses.
+/// `mtllib` and `map_Kd` paths are looked up relative to the file referring to them,
+/// faces before any `usemtl` get a white lambertian.
+pub fn load_obj<P: AsRef<Path>>(path: P) -> Result<Vec<TriangleMesh>, LoadError> {
+    let path = path.as_ref();
+    let source = read_file(path)?;
+    let mut parser = ObjParser::new(path);
+    for (number, line) in source.lines().enumerate() {
+        parser.parse_line(number + 1, line)?;
+    }
+    Ok(parser.into_meshes())
+}
+
+// position, uv and normal index of a face corner
+type Corner = (usize, Option<usize>, Option<usize>);
+
+struct FaceGroup {
+    material: Arc<dyn Material>,
+    vertices: HashMap<Corner, usize>,
+    corners: Vec<Corner>,
+    indices: Vec<[usize; 3]>,
+}
+
+impl FaceGroup {
+    fn new(material: Arc<dyn Material>) -> Self {
+        Self {
+            material,
+            vertices: HashMap::new(),
+            corners: Vec::new(),
+            indices: Vec::new(),
+        }
+    }
+    fn vertex(&mut self, corner: Corner) -> usize {
+        let corners = &mut self.corners;
+        *self.vertices.entry(corner).or_insert_with(|| {
+            corners.push(corner);
+            corners.len() - 1
+        })
+    }
+}
+
+struct ObjParser {
+    path: PathBuf,
+    positions: Vec<Point3>,
+    uvs: Vec<(f64, f64)>,
+    normals: Vec<Vec3>,
+    materials: HashMap<String, Arc<dyn Material>>,
+    groups: Vec<FaceGroup>,
+    group_by_material: HashMap<String, usize>,
+    current: Option<usize>,
+}
+
+impl ObjParser {
+    fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            positions: Vec::new(),
+            uvs: Vec::new(),
+            normals: Vec::new(),
+            materials: HashMap::new(),
+            groups: Vec::new(),
+            group_by_material: HashMap::new(),
+            current: None,
+        }
+    }
+    fn error(&self, line: usize, message: String) -> LoadError {
+        LoadError::Parse {
+            path: self.path.clone(),
+            line,
+            message,
+        }
+    }
+    fn parse_line(&mut self, number: usize, line: &str) -> Result<(), LoadError> {
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => return Ok(()),
+        };
+        let arguments: Vec<&str> = tokens.collect();
+        match keyword {
+            "v" => {
+                let v = parse_floats(&arguments, 3)
+                    .ok_or_else(|| self.error(number, "expected 3 coordinates".to_string()))?;
+                self.positions.push(Point3::new((v[0], v[1], v[2])));
+            }
+            "vt" => {
+                let vt = parse_floats(&arguments, 1).ok_or_else(|| {
+                    self.error(number, "expected a texture coordinate".to_string())
+                })?;
+                self.uvs.push((vt[0], vt.get(1).copied().unwrap_or(0.)));
+            }
+            "vn" => {
+                let vn = parse_floats(&arguments, 3)
+                    .ok_or_else(|| self.error(number, "expected 3 coordinates".to_string()))?;
+                self.normals.push(Vec3::new((vn[0], vn[1], vn[2])));
+            }
+            "f" => self.parse_face(number, &arguments)?,
+            "mtllib" => {
+                for name in arguments {
+                    let mtl_path = resolve(&self.path, name);
+                    self.materials.extend(load_mtl(&mtl_path)?);
+                }
+            }
+            "usemtl" => {
+                let name = arguments.join(" ");
+                let material =
+                    self.materials.get(&name).cloned().ok_or_else(|| {
+                        self.error(number, format!("unknown material {:?}", name))
+                    })?;
+                self.current = Some(self.group(name, material));
+            }
+            // objects, groups and smoothing groups don't change how faces are rendered
+            _ => {}
+        }
+        Ok(())
+    }
+    fn group(&mut self, name: String, material: Arc<dyn Material>) -> usize {
+        let groups = &mut self.groups;
+        *self.group_by_material.entry(name).or_insert_with(|| {
+            groups.push(FaceGroup::new(material));
+            groups.len() - 1
+        })
+    }
+    fn parse_face(&mut self, number: usize, arguments: &[&str]) -> Result<(), LoadError> {
+        if arguments.len() < 3 {
+            return Err(self.error(number, "a face needs at least 3 vertices".to_string()));
+        }
+        let mut corners = Vec::with_capacity(arguments.len());
+        for argument in arguments {
+            let corner = self
+                .parse_corner(argument)
+                .ok_or_else(|| self.error(number, format!("bad face vertex {:?}", argument)))?;
+            corners.push(corner);
+        }
+        let current = match self.current {
+            Some(current) => current,
+            None => {
+                let white = Arc::new(Lambertian::new(Color::new((0.73, 0.73, 0.73))));
+                self.group(String::new(), white)
+            }
+        };
+        self.current = Some(current);
+        let group = &mut self.groups[current];
+        let vertices: Vec<usize> = corners.into_iter().map(|c| group.vertex(c)).collect();
+        // polygons are split into a fan around their first vertex
+        for i in 1..vertices.len() - 1 {
+            group
+                .indices
+                .push([vertices[0], vertices[i], vertices[i + 1]]);
+        }
+        Ok(())
+    }
+    fn parse_corner(&self, argument: &str) -> Option<Corner> {
+        let mut parts = argument.split('/');
+        let position = resolve_index(parts.next()?, self.positions.len())?;
+        let uv = match parts.next() {
+            Some("") | None => None,
+            Some(index) => Some(resolve_index(index, self.uvs.len())?),
+        };
+        let normal = match parts.next() {
+            Some("") | None => None,
+            Some(index) => Some(resolve_index(index, self.normals.len())?),
+        };
+        Some((position, uv, normal))
+    }
+    fn into_meshes(self) -> Vec<TriangleMesh> {
+        let (positions, uvs, normals) = (self.positions, self.uvs, self.normals);
+        self.groups
+            .into_iter()
+            .filter(|group| !group.indices.is_empty())
+            .map(|group| {
+                // a buffer is only kept when every vertex of the mesh has an entry in it
+                let mesh_uvs: Option<Vec<(f64, f64)>> =
+                    group.corners.iter().map(|c| c.1.map(|i| uvs[i])).collect();
+                let mesh_normals: Option<Vec<Vec3>> = group
+                    .corners
+                    .iter()
+                    .map(|c| c.2.map(|i| normals[i]))
+                    .collect();
+                TriangleMesh::with_attributes(
+                    group.corners.iter().map(|c| positions[c.0]).collect(),
+                    mesh_normals,
+                    mesh_uvs,
+                    group.indices,
+                    group.material,
+                )
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone)]
+struct MtlSpec {
+    kd: Color,
+    ks: Color,
+    ke: Color,
+    ni: f64,
+    ns: f64,
+    d: f64,
+    illum: u32,
+    // the texture and the line naming it
+    map_kd: Option<(PathBuf, usize)>,
+}
+
+impl Default for MtlSpec {
+    fn default() -> Self {
+        Self {
+            kd: Color::triple(0.8),
+            ks: Color::default(),
+            ke: Color::default(),
+            ni: 1.5,
+            ns: 0.,
+            d: 1.,
+            illum: 2,
+            map_kd: None,
+        }
+    }
+}
+
+impl MtlSpec {
+    fn to_material(&self, path: &Path) -> Result<Arc<dyn Material>, LoadError> {
+        if self.ke.len_squared() > 0. {
+            return Ok(Arc::new(DiffuseLight::with_solid_color(self.ke)));
+        }
+        // illumination models 4, 6, 7 and 9 are the ones with refraction
+        if matches!(self.illum, 4 | 6 | 7 | 9) || (self.d < 1. && self.ni > 1.) {
+            return Ok(Arc::new(Dielectric::new(self.ni)));
+        }
+        let mirror = matches!(self.illum, 3 | 5);
+        if mirror || (self.kd.len_squared() == 0. && self.ks.len_squared() > 0.) {
+            // inverse of the phong exponent `Metal` derives from its fuzz
+            let fuzz = (2. / (self.ns.max(0.) + 2.)).sqrt();
+            return Ok(Arc::new(Metal::new(self.ks, fuzz)));
+        }
+        match &self.map_kd {
+            Some((texture, line)) => {
+                let texture = ImageTexture::open(texture).map_err(|source| LoadError::Texture {
+                    path: path.to_path_buf(),
+                    line: *line,
+                    texture: texture.clone(),
+                    source,
+                })?;
+                Ok(Arc::new(Lambertian::with_texture(Arc::new(texture))))
+            }
+            None => Ok(Arc::new(Lambertian::new(self.kd))),
+        }
+    }
+}
+
+fn load_mtl(path: &Path) -> Result<HashMap<String, Arc<dyn Material>>, LoadError> {
+    let source = read_file(path)?;
+    let error = |line: usize, message: &str| LoadError::Parse {
+        path: path.to_path_buf(),
+        line,
+        message: message.to_string(),
+    };
+    let mut specs: Vec<(String, MtlSpec)> = Vec::new();
+    for (number, line) in source.lines().enumerate() {
+        let number = number + 1;
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let arguments: Vec<&str> = tokens.collect();
+        if keyword == "newmtl" {
+            specs.push((arguments.join(" "), MtlSpec::default()));
+            continue;
+        }
+        let spec = match specs.last_mut() {
+            Some((_, spec)) => spec,
+            None => return Err(error(number, "material statement before newmtl")),
+        };
+        let color = || {
+            parse_floats(&arguments, 3)
+                .map(|c| Color::new((c[0], c[1], c[2])))
+                .ok_or_else(|| error(number, "expected an rgb color"))
+        };
+        let float = || {
+            parse_floats(&arguments, 1)
+                .map(|f| f[0])
+                .ok_or_else(|| error(number, "expected a number"))
+        };
+        match keyword {
+            "Kd" => spec.kd = color()?,
+            "Ks" => spec.ks = color()?,
+            "Ke" => spec.ke = color()?,
+            "Ni" => spec.ni = float()?,
+            "Ns" => spec.ns = float()?,
+            "d" => spec.d = float()?,
+            "Tr" => spec.d = 1. - float()?,
+            "illum" => spec.illum = float()? as u32,
+            // options like `-s 1 1 1` come first, the file name is last
+            "map_Kd" => match arguments.last() {
+                Some(name) => spec.map_kd = Some((resolve(path, name), number)),
+                None => return Err(error(number, "expected a texture file")),
+            },
+            _ => {}
+        }
+    }
+    let mut materials = HashMap::new();
+    for (name, spec) in specs {
+        materials.insert(name, spec.to_material(path)?);
+    }
+    Ok(materials)
+}
+
+fn read_file(path: &Path) -> Result<String, LoadError> {
+    fs::read_to_string(path).map_err(|source| LoadError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn resolve(referrer: &Path, name: &str) -> PathBuf {
+    match referrer.parent() {
+        Some(dir) => dir.join(name),
+        None => PathBuf::from(name),
+    }
+}
+
+// obj indices start at 1, negative ones count back from the last element read so far
+fn resolve_index(index: &str, count: usize) -> Option<usize> {
+    let index: isize = index.parse().ok()?;
+    let resolved = match index {
+        0 => return None,
+        i if i > 0 => i - 1,
+        i => count as isize + i,
+    };
+    if resolved < 0 || resolved as usize >= count {
+        return None;
+    }
+    Some(resolved as usize)
+}
+
+fn parse_floats(arguments: &[&str], count: usize) -> Option<Vec<f64>> {
+    if arguments.len() < count {
+        return None;
+    }
+    arguments.iter().map(|a| a.parse().ok()).collect()
+}
@@ -0,0 +1,398 @@
+use std::{fs, path::Path, sync::Arc};
+
+use crate::{LoadError, Material, Point3, TriangleMesh, Vec3};
+
+/// loads a ply mesh in ascii or binary little endian encoding. `nx ny nz` vertex
+/// properties become shading normals and `u v` (or `s t`) become uvs, polygons are
+/// split into triangles and any other element is skipped.
+pub fn load_ply<P: AsRef<Path>>(
+    path: P,
+    mat_ptr: Arc<dyn Material>,
+) -> Result<TriangleMesh, LoadError> {
+    let path = path.as_ref();
+    let bytes = fs::read(path).map_err(|source| LoadError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let header = Header::parse(path, &bytes)?;
+    let mut body = match header.encoding {
+        Encoding::Ascii => {
+            let text = std::str::from_utf8(&bytes[header.body_offset..]).map_err(|_| {
+                LoadError::Malformed {
+                    path: path.to_path_buf(),
+                    message: "ascii body is not valid utf-8".to_string(),
+                }
+            })?;
+            Body::ascii(text, header.lines)
+        }
+        Encoding::BinaryLittleEndian => Body::Binary {
+            bytes: &bytes[header.body_offset..],
+            position: 0,
+        },
+    };
+
+    let mut vertices = VertexData::default();
+    let mut faces: Vec<[usize; 3]> = Vec::new();
+    for element in &header.elements {
+        for _ in 0..element.count {
+            match element.name.as_str() {
+                "vertex" => vertices.read(path, element, &mut body)?,
+                "face" => read_face(path, element, &mut body, &mut faces)?,
+                _ => {
+                    for property in &element.properties {
+                        property.read(path, &mut body)?;
+                    }
+                }
+            }
+        }
+    }
+    if let Some(&index) = faces
+        .iter()
+        .flatten()
+        .find(|&&i| i >= vertices.positions.len())
+    {
+        return Err(LoadError::Malformed {
+            path: path.to_path_buf(),
+            message: format!(
+                "face refers to vertex {} but there are only {}",
+                index,
+                vertices.positions.len()
+            ),
+        });
+    }
+    Ok(vertices.into_mesh(faces, mat_ptr))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Encoding {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "char" | "int8" => Some(Scalar::I8),
+            "uchar" | "uint8" => Some(Scalar::U8),
+            "short" | "int16" => Some(Scalar::I16),
+            "ushort" | "uint16" => Some(Scalar::U16),
+            "int" | "int32" => Some(Scalar::I32),
+            "uint" | "uint32" => Some(Scalar::U32),
+            "float" | "float32" => Some(Scalar::F32),
+            "double" | "float64" => Some(Scalar::F64),
+            _ => None,
+        }
+    }
+    fn size(&self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Property {
+    Scalar {
+        name: String,
+        kind: Scalar,
+    },
+    List {
+        name: String,
+        count: Scalar,
+        item: Scalar,
+    },
+}
+
+impl Property {
+    fn name(&self) -> &str {
+        match self {
+            Property::Scalar { name, .. } | Property::List { name, .. } => name,
+        }
+    }
+    fn read(&self, path: &Path, body: &mut Body) -> Result<Vec<f64>, LoadError> {
+        match self {
+            Property::Scalar { kind, .. } => Ok(vec![body.read(path, *kind)?]),
+            Property::List { count, item, .. } => {
+                let count = body.read(path, *count)?;
+                if count < 0. {
+                    return Err(body.error(path, format!("negative list length {}", count)));
+                }
+                (0..count as usize)
+                    .map(|_| body.read(path, *item))
+                    .collect()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+struct Header {
+    encoding: Encoding,
+    elements: Vec<Element>,
+    body_offset: usize,
+    lines: usize,
+}
+
+impl Header {
+    fn parse(path: &Path, bytes: &[u8]) -> Result<Self, LoadError> {
+        let error = |line: usize, message: String| LoadError::Parse {
+            path: path.to_path_buf(),
+            line,
+            message,
+        };
+        let mut encoding = None;
+        let mut elements: Vec<Element> = Vec::new();
+        let mut offset = 0;
+        let mut line = 0;
+        loop {
+            let end = match bytes[offset..].iter().position(|&b| b == b'\n') {
+                Some(end) => offset + end,
+                None => return Err(error(line + 1, "header has no end_header".to_string())),
+            };
+            line += 1;
+            let text = String::from_utf8_lossy(&bytes[offset..end]);
+            offset = end + 1;
+            let tokens: Vec<&str> = text.split_whitespace().collect();
+            if line == 1 {
+                if tokens != ["ply"] {
+                    return Err(error(line, "not a ply file".to_string()));
+                }
+                continue;
+            }
+            match tokens.as_slice() {
+                ["format", "ascii", _] => encoding = Some(Encoding::Ascii),
+                ["format", "binary_little_endian", _] => {
+                    encoding = Some(Encoding::BinaryLittleEndian)
+                }
+                ["format", format, ..] => {
+                    return Err(error(line, format!("unsupported format {}", format)))
+                }
+                ["element", name, count] => {
+                    let count = count
+                        .parse()
+                        .map_err(|_| error(line, format!("bad element count {:?}", count)))?;
+                    elements.push(Element {
+                        name: name.to_string(),
+                        count,
+                        properties: Vec::new(),
+                    });
+                }
+                ["property", rest @ ..] => {
+                    let property = match rest {
+                        ["list", count, item, name] => {
+                            match (Scalar::parse(count), Scalar::parse(item)) {
+                                (Some(count), Some(item)) => Property::List {
+                                    name: name.to_string(),
+                                    count,
+                                    item,
+                                },
+                                _ => return Err(error(line, "unknown list type".to_string())),
+                            }
+                        }
+                        [kind, name] => match Scalar::parse(kind) {
+                            Some(kind) => Property::Scalar {
+                                name: name.to_string(),
+                                kind,
+                            },
+                            None => return Err(error(line, format!("unknown type {}", kind))),
+                        },
+                        _ => return Err(error(line, "malformed property".to_string())),
+                    };
+                    match elements.last_mut() {
+                        Some(element) => element.properties.push(property),
+                        None => return Err(error(line, "property before element".to_string())),
+                    }
+                }
+                ["end_header"] => break,
+                ["comment", ..] | ["obj_info", ..] | [] => {}
+                _ => return Err(error(line, format!("unexpected header line {:?}", text))),
+            }
+        }
+        match encoding {
+            Some(encoding) => Ok(Self {
+                encoding,
+                elements,
+                body_offset: offset,
+                lines: line,
+            }),
+            None => Err(error(line, "header has no format".to_string())),
+        }
+    }
+}
+
+enum Body<'a> {
+    Ascii {
+        tokens: Vec<(usize, &'a str)>,
+        position: usize,
+    },
+    Binary {
+        bytes: &'a [u8],
+        position: usize,
+    },
+}
+
+impl<'a> Body<'a> {
+    fn ascii(text: &'a str, header_lines: usize) -> Self {
+        let tokens = text
+            .lines()
+            .enumerate()
+            .flat_map(|(i, line)| {
+                line.split_whitespace()
+                    .map(move |token| (header_lines + i + 1, token))
+            })
+            .collect();
+        Body::Ascii {
+            tokens,
+            position: 0,
+        }
+    }
+    fn error(&self, path: &Path, message: String) -> LoadError {
+        match self {
+            Body::Ascii { tokens, position } => LoadError::Parse {
+                path: path.to_path_buf(),
+                line: tokens
+                    .get(position.saturating_sub(1))
+                    .map_or(0, |token| token.0),
+                message,
+            },
+            Body::Binary { position, .. } => LoadError::Malformed {
+                path: path.to_path_buf(),
+                message: format!("{} at byte {} of the body", message, position),
+            },
+        }
+    }
+    fn read(&mut self, path: &Path, kind: Scalar) -> Result<f64, LoadError> {
+        match self {
+            Body::Ascii { tokens, position } => {
+                let token = match tokens.get(*position) {
+                    Some(token) => token.1,
+                    None => return Err(self.error(path, "unexpected end of file".to_string())),
+                };
+                *position += 1;
+                token
+                    .parse()
+                    .map_err(|_| self.error(path, format!("bad number {:?}", token)))
+            }
+            Body::Binary { bytes, position } => {
+                let size = kind.size();
+                if *position + size > bytes.len() {
+                    return Err(self.error(path, "unexpected end of file".to_string()));
+                }
+                let b = &bytes[*position..*position + size];
+                *position += size;
+                Ok(match kind {
+                    Scalar::I8 => b[0] as i8 as f64,
+                    Scalar::U8 => b[0] as f64,
+                    Scalar::I16 => i16::from_le_bytes([b[0], b[1]]) as f64,
+                    Scalar::U16 => u16::from_le_bytes([b[0], b[1]]) as f64,
+                    Scalar::I32 => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    Scalar::U32 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    Scalar::F32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    Scalar::F64 => {
+                        f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]])
+                    }
+                })
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct VertexData {
+    positions: Vec<Point3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<(f64, f64)>,
+}
+
+impl VertexData {
+    fn read(&mut self, path: &Path, element: &Element, body: &mut Body) -> Result<(), LoadError> {
+        // x y z, nx ny nz, u v
+        let mut values = [None; 8];
+        for property in &element.properties {
+            let value = property.read(path, body)?;
+            let slot = match property.name() {
+                "x" => 0,
+                "y" => 1,
+                "z" => 2,
+                "nx" => 3,
+                "ny" => 4,
+                "nz" => 5,
+                "u" | "s" | "texture_u" | "texture_s" => 6,
+                "v" | "t" | "texture_v" | "texture_t" => 7,
+                _ => continue,
+            };
+            values[slot] = value.first().copied();
+        }
+        match (values[0], values[1], values[2]) {
+            (Some(x), Some(y), Some(z)) => self.positions.push(Point3::new((x, y, z))),
+            _ => return Err(body.error(path, "vertex has no x, y and z".to_string())),
+        }
+        if let (Some(x), Some(y), Some(z)) = (values[3], values[4], values[5]) {
+            self.normals.push(Vec3::new((x, y, z)));
+        }
+        if let (Some(u), Some(v)) = (values[6], values[7]) {
+            self.uvs.push((u, v));
+        }
+        Ok(())
+    }
+    fn into_mesh(self, faces: Vec<[usize; 3]>, mat_ptr: Arc<dyn Material>) -> TriangleMesh {
+        let count = self.positions.len();
+        TriangleMesh::with_attributes(
+            self.positions,
+            Some(self.normals).filter(|normals| normals.len() == count && count > 0),
+            Some(self.uvs).filter(|uvs| uvs.len() == count && count > 0),
+            faces,
+            mat_ptr,
+        )
+    }
+}
+
+fn read_face(
+    path: &Path,
+    element: &Element,
+    body: &mut Body,
+    faces: &mut Vec<[usize; 3]>,
+) -> Result<(), LoadError> {
+    for property in &element.properties {
+        let values = property.read(path, body)?;
+        if !matches!(property.name(), "vertex_indices" | "vertex_index") {
+            continue;
+        }
+        if values.len() < 3 {
+            return Err(body.error(path, "a face needs at least 3 vertices".to_string()));
+        }
+        let mut index = Vec::with_capacity(values.len());
+        for &value in &values {
+            if value < 0. || value.fract() != 0. {
+                return Err(body.error(path, format!("bad vertex index {}", value)));
+            }
+            index.push(value as usize);
+        }
+        // polygons are split into a fan around their first vertex
+        for i in 1..index.len() - 1 {
+            faces.push([index[0], index[i], index[i + 1]]);
+        }
+    }
+    Ok(())
+}
@@ -1,20 +1,41 @@
-
 use std::sync::Arc;
 
-use crate::{Color, HitRecord, Point3, Ray, SolidColor, Texture, Vec3, random_float};
+use crate::{Color, HitRecord, Point3, Ray, Sampler, SolidColor, Texture, Vec3, ONB, PI};
 
+/// a sampled bounce, `attenuation` already divides the bsdf by `pdf`.
+/// specular bounces have no meaningful pdf and can't be reached by light sampling.
+#[derive(Clone, Debug)]
+pub struct ScatterRecord {
+    pub attenuation: Color,
+    pub scattered: Ray,
+    pub pdf: f64,
+    pub is_specular: bool,
+}
 
-pub trait Material: Send + Sync{
+/// `wi` points towards where the light comes from and `wo` towards the viewer,
+/// neither needs to be normalized.
+pub trait Material: Send + Sync {
     fn scatter(
         &self,
         r_in: &Ray,
-        rec: &mut HitRecord,
-        attention: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool;
+        rec: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord>;
     fn emitted(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
         Color::new((0., 0., 0.))
     }
+    /// bsdf times the cosine term, black for specular materials.
+    fn eval(&self, _rec: &HitRecord, _wi: &Vec3, _wo: &Vec3) -> Color {
+        Color::default()
+    }
+    /// density `scatter` picks `wi` with, 0 for specular materials.
+    fn pdf(&self, _rec: &HitRecord, _wi: &Vec3, _wo: &Vec3) -> f64 {
+        0.
+    }
+    /// the surface color as the albedo aov sees it, what a bounce is tinted with.
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        Color::default()
+    }
 }
 
 impl Default for HitRecord {
@@ -27,6 +48,7 @@ impl Default for HitRecord {
             mat_ptr: None,
             u: 0.,
             v: 0.,
+            object_id: 0,
         }
     }
 }
@@ -49,17 +71,33 @@ impl Material for Lambertian {
     fn scatter(
         &self,
         r_in: &Ray,
-        rec: &mut HitRecord,
-        attention: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool {
-        let mut scatter_direction = rec.normal + Vec3::random_unit_vector();
+        rec: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        let mut scatter_direction = rec.normal + Vec3::random_unit_vector(sampler);
         if scatter_direction.near_zero() {
             scatter_direction = rec.normal;
         }
-        *scattered = Ray::new(rec.p, scatter_direction, r_in.time());
-        *attention = self.albedo.value(rec.u, rec.v, &rec.p);
-        return true;
+        let wo = -*r_in.direction();
+        Some(ScatterRecord {
+            attenuation: self.albedo.value(rec.u, rec.v, &rec.p),
+            pdf: self.pdf(rec, &scatter_direction, &wo),
+            scattered: Ray::new(rec.p, scatter_direction, r_in.time()),
+            is_specular: false,
+        })
+    }
+    fn eval(&self, rec: &HitRecord, wi: &Vec3, wo: &Vec3) -> Color {
+        self.albedo.value(rec.u, rec.v, &rec.p) * self.pdf(rec, wi, wo)
+    }
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.albedo.value(rec.u, rec.v, &rec.p)
+    }
+    fn pdf(&self, rec: &HitRecord, wi: &Vec3, _wo: &Vec3) -> f64 {
+        let cosine = rec.normal.dot(&wi.unit_vector());
+        if cosine < 0. {
+            return 0.;
+        }
+        cosine / PI
     }
 }
 
@@ -68,29 +106,78 @@ pub struct Metal {
     pub(crate) fuzz: f64,
 }
 impl Metal {
+    /// `fuzz` is the roughness of a phong lobe around the mirror direction with exponent
+    /// `2 / fuzz² - 2`, not the radius of the book's sphere of random offsets. 0 is a
+    /// perfect mirror and 1 (the most allowed) spreads the lobe evenly over the hemisphere
+    /// around the mirror direction. whatever part of the lobe points below the surface is
+    /// absorbed, so rough metals get darker towards grazing angles.
     pub fn new(albedo: Color, fuzz: f64) -> Self {
         Self {
             albedo,
             fuzz: fuzz.min(1.),
         }
     }
+    // phong lobe around the mirror direction, exponent 0 at fuzz 1 is uniform over the
+    // hemisphere around it, which only matches the surface's at normal incidence.
+    fn exponent(&self) -> f64 {
+        2. / self.fuzz.powi(2) - 2.
+    }
 }
 impl Material for Metal {
     fn scatter(
         &self,
         r_in: &Ray,
-        rec: &mut HitRecord,
-        attention: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool {
-        let reflected = r_in.direction().reflect(rec.normal);
-        *scattered = Ray::new(
-            rec.clone().p,
-            reflected + self.fuzz * Vec3::random_in_unit_sphere(),
-            r_in.time(),
-        );
-        *attention = self.albedo;
-        scattered.direction().dot(&rec.normal) > 0.
+        rec: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        let reflected = r_in.direction().unit_vector().reflect(rec.normal);
+        let direction = if self.fuzz > 0. {
+            let (u, v) = sampler.get_2d();
+            let cos_alpha = u.powf(1. / (self.exponent() + 1.));
+            let sin_alpha = (1. - cos_alpha.powi(2)).sqrt();
+            let phi = 2. * PI * v;
+            ONB::build_from_w(&reflected).local(&Vec3::new((
+                phi.cos() * sin_alpha,
+                phi.sin() * sin_alpha,
+                cos_alpha,
+            )))
+        } else {
+            reflected
+        };
+        if direction.dot(&rec.normal) <= 0. {
+            return None;
+        }
+        let wo = -*r_in.direction();
+        Some(ScatterRecord {
+            attenuation: self.albedo,
+            pdf: self.pdf(rec, &direction, &wo),
+            scattered: Ray::new(rec.p, direction, r_in.time()),
+            is_specular: self.fuzz <= 0.,
+        })
+    }
+    fn eval(&self, rec: &HitRecord, wi: &Vec3, wo: &Vec3) -> Color {
+        // the lobe is sampled exactly, so the bsdf times cosine is the albedo scaled by the pdf.
+        // `pdf` isn't renormalised over the directions above the surface: `scatter` absorbs
+        // the samples below it rather than drawing again, so above the surface the whole
+        // lobe's density is the one the samples actually have, and both estimators of mis
+        // see the same lobe with the same energy lost.
+        self.albedo * self.pdf(rec, wi, wo)
+    }
+    fn pdf(&self, rec: &HitRecord, wi: &Vec3, wo: &Vec3) -> f64 {
+        if self.fuzz <= 0. {
+            return 0.;
+        }
+        let direction = wi.unit_vector();
+        let reflected = (-*wo).unit_vector().reflect(rec.normal);
+        let cos_alpha = reflected.dot(&direction);
+        if cos_alpha <= 0. || direction.dot(&rec.normal) <= 0. {
+            return 0.;
+        }
+        let exponent = self.exponent();
+        (exponent + 1.) / (2. * PI) * cos_alpha.powf(exponent)
+    }
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        self.albedo
     }
 }
 
@@ -112,11 +199,9 @@ impl Material for Dielectric {
     fn scatter(
         &self,
         r_in: &Ray,
-        rec: &mut HitRecord,
-        attention: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool {
-        *attention = Color::new((1., 1., 1.));
+        rec: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
         let refraction_ratio = match rec.front_face {
             true => 1. / self.ir,
             false => self.ir,
@@ -126,13 +211,21 @@ impl Material for Dielectric {
         let sin_theta = (1. - cos_theta.powi(2)).sqrt();
         let cannot_refract = refraction_ratio * sin_theta > 1.;
         let direction: Vec3;
-        if cannot_refract || Self::reflectance(cos_theta, refraction_ratio) > random_float() {
+        if cannot_refract || Self::reflectance(cos_theta, refraction_ratio) > sampler.get_1d() {
             direction = unit_direction.reflect(rec.normal);
         } else {
             direction = unit_direction.refract(rec.normal, refraction_ratio);
         }
-        *scattered = Ray::new(rec.p, direction, r_in.time());
-        true
+        Some(ScatterRecord {
+            attenuation: Color::new((1., 1., 1.)),
+            scattered: Ray::new(rec.p, direction, r_in.time()),
+            pdf: 0.,
+            is_specular: true,
+        })
+    }
+    // glass passes all the light on, just not in one direction
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        Color::new((1., 1., 1.))
     }
 }
 
@@ -155,15 +248,19 @@ impl Material for DiffuseLight {
     fn scatter(
         &self,
         _r_in: &Ray,
-        _rec: &mut HitRecord,
-        _attention: &mut Color,
-        _scattered: &mut Ray,
-    ) -> bool {
-        false
+        _rec: &HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        None
     }
     fn emitted(&self, u: f64, v: f64, p: &Point3) -> Color {
         self.emit.value(u, v, p)
     }
+    // the emission clamped, so lights stand out from their surroundings
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        let c = self.emitted(rec.u, rec.v, &rec.p);
+        Color::new((c.x().min(1.), c.y().min(1.), c.z().min(1.)))
+    }
 }
 
 pub struct Isotropic {
@@ -175,8 +272,8 @@ impl Isotropic {
         Self { albedo }
     }
     pub fn from_color(c: Color) -> Self {
-        Self{ 
-            albedo: Arc::new(SolidColor::new(c))
+        Self {
+            albedo: Arc::new(SolidColor::new(c)),
         }
     }
 }
@@ -185,12 +282,23 @@ impl Material for Isotropic {
     fn scatter(
         &self,
         r_in: &Ray,
-        rec: &mut HitRecord,
-        attention: &mut Color,
-        scattered: &mut Ray,
-    ) -> bool {
-        *scattered = Ray::new(rec.p, Vec3::random_in_unit_sphere(), r_in.time());
-        *attention = self.albedo.value(rec.u, rec.v, &rec.p);
-        true
-    }
-}
\ No newline at end of file
+        rec: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        Some(ScatterRecord {
+            attenuation: self.albedo.value(rec.u, rec.v, &rec.p),
+            scattered: Ray::new(rec.p, Vec3::random_unit_vector(sampler), r_in.time()),
+            pdf: 1. / (4. * PI),
+            is_specular: false,
+        })
+    }
+    fn eval(&self, rec: &HitRecord, _wi: &Vec3, _wo: &Vec3) -> Color {
+        self.albedo.value(rec.u, rec.v, &rec.p) / (4. * PI)
+    }
+    fn pdf(&self, _rec: &HitRecord, _wi: &Vec3, _wo: &Vec3) -> f64 {
+        1. / (4. * PI)
+    }
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.albedo.value(rec.u, rec.v, &rec.p)
+    }
+}
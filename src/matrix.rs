@@ -0,0 +1,266 @@
+use std::ops;
+
+use crate::{degree_to_radians, Point3, Vec3, AABB};
+
+/// affine transform acting on column vectors, indexed `m[row][column]`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mat4 {
+    pub(crate) m: [[f64; 4]; 4],
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Mat4 {
+    pub fn new(m: [[f64; 4]; 4]) -> Self {
+        Self { m }
+    }
+    pub fn identity() -> Self {
+        Self::scaling(Vec3::triple(1.))
+    }
+    pub fn translation(offset: Vec3) -> Self {
+        Self::new([
+            [1., 0., 0., offset.x()],
+            [0., 1., 0., offset.y()],
+            [0., 0., 1., offset.z()],
+            [0., 0., 0., 1.],
+        ])
+    }
+    pub fn scaling(factors: Vec3) -> Self {
+        Self::new([
+            [factors.x(), 0., 0., 0.],
+            [0., factors.y(), 0., 0.],
+            [0., 0., factors.z(), 0.],
+            [0., 0., 0., 1.],
+        ])
+    }
+    /// rotation by `angle` degrees around `axis`, counterclockwise when the axis points
+    /// at the viewer.
+    pub fn rotation(axis: Vec3, angle: f64) -> Self {
+        let a = axis.unit_vector();
+        let (x, y, z) = (a.x(), a.y(), a.z());
+        let (sin, cos) = degree_to_radians(angle).sin_cos();
+        let t = 1. - cos;
+        Self::new([
+            [
+                t * x * x + cos,
+                t * x * y - sin * z,
+                t * x * z + sin * y,
+                0.,
+            ],
+            [
+                t * x * y + sin * z,
+                t * y * y + cos,
+                t * y * z - sin * x,
+                0.,
+            ],
+            [
+                t * x * z - sin * y,
+                t * y * z + sin * x,
+                t * z * z + cos,
+                0.,
+            ],
+            [0., 0., 0., 1.],
+        ])
+    }
+    /// scales first, then rotates, then translates.
+    pub fn from_trs(translation: Vec3, rotation: Quaternion, scale: Vec3) -> Self {
+        Self::translation(translation) * rotation.to_mat4() * Self::scaling(scale)
+    }
+    pub fn transpose(&self) -> Self {
+        let mut m = [[0.; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        Self::new(m)
+    }
+    /// gauss-jordan elimination with partial pivoting, `None` when the matrix is singular.
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.m;
+        let mut inv = Self::identity().m;
+        for column in 0..4 {
+            let pivot = (column..4)
+                .max_by(|&i, &j| a[i][column].abs().total_cmp(&a[j][column].abs()))
+                .unwrap();
+            if a[pivot][column].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(column, pivot);
+            inv.swap(column, pivot);
+            let scale = 1. / a[column][column];
+            for j in 0..4 {
+                a[column][j] *= scale;
+                inv[column][j] *= scale;
+            }
+            for row in 0..4 {
+                let factor = a[row][column];
+                if row == column || factor == 0. {
+                    continue;
+                }
+                for j in 0..4 {
+                    a[row][j] -= factor * a[column][j];
+                    inv[row][j] -= factor * inv[column][j];
+                }
+            }
+        }
+        Some(Self::new(inv))
+    }
+    /// of the linear part, how much the transform scales volumes (negative if it mirrors).
+    pub fn determinant(&self) -> f64 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+    pub fn transform_point(&self, p: &Point3) -> Point3 {
+        self.transform_vector(p) + Vec3::new((self.m[0][3], self.m[1][3], self.m[2][3]))
+    }
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new((
+            m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+            m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+            m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+        ))
+    }
+    /// normals go through the inverse transpose, so call this on the inverse of the
+    /// transform the surface went through. the result isn't normalized.
+    pub fn transform_normal(&self, n: &Vec3) -> Vec3 {
+        self.transpose().transform_vector(n)
+    }
+    /// the smallest box around the transformed corners of `b` (Arvo 1990).
+    pub fn transform_box(&self, b: &AABB) -> AABB {
+        let mut min = Vec3::new((self.m[0][3], self.m[1][3], self.m[2][3]));
+        let mut max = min;
+        for i in 0..3 {
+            for j in 0..3 {
+                let e = self.m[i][j] * b.min()[j];
+                let f = self.m[i][j] * b.max()[j];
+                min[i] += e.min(f);
+                max[i] += e.max(f);
+            }
+        }
+        AABB::new(min, max)
+    }
+}
+
+impl ops::Mul for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut m = [[0.; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Self::new(m)
+    }
+}
+
+/// unit quaternions stand for rotations, `w` is the real part.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quaternion {
+    pub(crate) w: f64,
+    pub(crate) v: Vec3,
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Quaternion {
+    pub fn new(w: f64, v: Vec3) -> Self {
+        Self { w, v }
+    }
+    pub fn identity() -> Self {
+        Self::new(1., Vec3::default())
+    }
+    /// rotation by `angle` degrees around `axis`, turning the same way as `Mat4::rotation`.
+    pub fn from_axis_angle(axis: Vec3, angle: f64) -> Self {
+        let (sin, cos) = (0.5 * degree_to_radians(angle)).sin_cos();
+        Self::new(cos, sin * axis.unit_vector())
+    }
+    pub fn dot(&self, other: &Self) -> f64 {
+        self.w * other.w + self.v.dot(&other.v)
+    }
+    pub fn normalize(&self) -> Self {
+        let len = self.dot(self).sqrt();
+        Self::new(self.w / len, self.v / len)
+    }
+    pub fn conjugate(&self) -> Self {
+        Self::new(self.w, -self.v)
+    }
+    pub fn rotate(&self, v: &Vec3) -> Vec3 {
+        // v + 2w(q × v) + 2q × (q × v)
+        let t = 2. * self.v.cross(v);
+        *v + self.w * t + self.v.cross(&t)
+    }
+    /// angle in radians of the rotation taking `self` to `other`, the short way round.
+    pub fn angle_to(&self, other: &Self) -> f64 {
+        2. * self.dot(other).abs().min(1.).acos()
+    }
+    /// constant speed interpolation from `self` at `t = 0` to `other` at `t = 1`,
+    /// along the shorter of the two arcs.
+    pub fn slerp(&self, other: &Self, t: f64) -> Self {
+        let mut cos = self.dot(other);
+        let mut other = *other;
+        if cos < 0. {
+            cos = -cos;
+            other = Self::new(-other.w, -other.v);
+        }
+        // nearly the same rotation, where sin θ would be too small to divide by
+        let (a, b) = if cos > 0.9995 {
+            (1. - t, t)
+        } else {
+            let theta = cos.acos();
+            let sin = theta.sin();
+            (((1. - t) * theta).sin() / sin, (t * theta).sin() / sin)
+        };
+        Self::new(a * self.w + b * other.w, a * self.v + b * other.v).normalize()
+    }
+    pub fn to_mat4(&self) -> Mat4 {
+        let q = self.normalize();
+        let (w, x, y, z) = (q.w, q.v.x(), q.v.y(), q.v.z());
+        Mat4::new([
+            [
+                1. - 2. * (y * y + z * z),
+                2. * (x * y - w * z),
+                2. * (x * z + w * y),
+                0.,
+            ],
+            [
+                2. * (x * y + w * z),
+                1. - 2. * (x * x + z * z),
+                2. * (y * z - w * x),
+                0.,
+            ],
+            [
+                2. * (x * z - w * y),
+                2. * (y * z + w * x),
+                1. - 2. * (x * x + y * y),
+                0.,
+            ],
+            [0., 0., 0., 1.],
+        ])
+    }
+}
+
+impl ops::Mul for Quaternion {
+    type Output = Quaternion;
+
+    /// applies `rhs` first, then `self`.
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self::new(
+            self.w * rhs.w - self.v.dot(&rhs.v),
+            self.w * rhs.v + rhs.w * self.v + self.v.cross(&rhs.v),
+        )
+    }
+}
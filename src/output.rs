@@ -0,0 +1,290 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::{Color, Framebuffer};
+
+impl Framebuffer {
+    /// portable float map, 32 bit little endian rgb. alpha is dropped, the format has none.
+    pub fn write_pfm<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        // a negative scale marks little endian
+        write!(writer, "PF\n{} {}\n-1.0\n", self.width(), self.height())?;
+        // pfm rows go bottom up
+        for y in (0..self.height()).rev() {
+            for x in 0..self.width() {
+                let c = self.get_pixel(x, y);
+                for value in [c.x(), c.y(), c.z()] {
+                    writer.write_all(&(value as f32).to_le_bytes())?;
+                }
+            }
+        }
+        writer.flush()
+    }
+
+    /// radiance rgbe, run length encoded where the format allows it. alpha is dropped and
+    /// negative values become 0.
+    pub fn write_hdr<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        write!(
+            writer,
+            "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+            self.height(),
+            self.width()
+        )?;
+        let width = self.width();
+        for y in 0..self.height() {
+            let scanline: Vec<[u8; 4]> =
+                (0..width).map(|x| to_rgbe(&self.get_pixel(x, y))).collect();
+            // run length encoded scanlines only exist for these widths
+            if !(8..0x8000).contains(&width) {
+                for pixel in &scanline {
+                    writer.write_all(pixel)?;
+                }
+                continue;
+            }
+            writer.write_all(&[2, 2, (width >> 8) as u8, width as u8])?;
+            for channel in 0..4 {
+                let values: Vec<u8> = scanline.iter().map(|pixel| pixel[channel]).collect();
+                write_rle(&mut writer, &values)?;
+            }
+        }
+        writer.flush()
+    }
+}
+
+fn to_rgbe(c: &Color) -> [u8; 4] {
+    let (r, g, b) = (c.x().max(0.), c.y().max(0.), c.z().max(0.));
+    let v = r.max(g).max(b);
+    if v < 1e-32 {
+        return [0; 4];
+    }
+    // v = m * 2^e with m in [0.5, 1)
+    let mut e = v.log2().floor() as i32 + 1;
+    if v / 2f64.powi(e) >= 1. {
+        e += 1;
+    }
+    if e > 127 {
+        return [255, 255, 255, 255];
+    }
+    let scale = 256. / 2f64.powi(e);
+    [
+        (r * scale) as u8,
+        (g * scale) as u8,
+        (b * scale) as u8,
+        (e + 128) as u8,
+    ]
+}
+
+// runs of 4 or more become a count above 128 and the value, the rest goes out in
+// literal dumps of up to 128 values
+fn write_rle<W: Write>(writer: &mut W, values: &[u8]) -> io::Result<()> {
+    const MIN_RUN: usize = 4;
+    let mut start = 0;
+    while start < values.len() {
+        let mut run_start = start;
+        let mut run = 0;
+        while run_start < values.len() {
+            run = values[run_start..]
+                .iter()
+                .take(127)
+                .take_while(|&&v| v == values[run_start])
+                .count();
+            if run >= MIN_RUN {
+                break;
+            }
+            run_start += run;
+        }
+        if run < MIN_RUN {
+            run_start = values.len();
+        }
+        while start < run_start {
+            let count = (run_start - start).min(128);
+            writer.write_all(&[count as u8])?;
+            writer.write_all(&values[start..start + count])?;
+            start += count;
+        }
+        if run >= MIN_RUN {
+            writer.write_all(&[128 + run as u8, values[run_start]])?;
+            start = run_start + run;
+        }
+    }
+    Ok(())
+}
+
+/// how `ExrImage` stores its values.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExrPixelType {
+    #[default]
+    Half,
+    Float,
+}
+
+/// an uncompressed scanline openexr image holding any number of named channels, such as
+/// several rgba layers next to each other.
+#[derive(Clone, Debug)]
+pub struct ExrImage {
+    width: u32,
+    height: u32,
+    channels: Vec<(String, Vec<f32>)>,
+}
+
+impl ExrImage {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            channels: Vec::new(),
+        }
+    }
+    /// adds `framebuffer` as the channels `name.R`, `name.G`, `name.B` and `name.A`, or
+    /// plain `R`, `G`, `B` and `A` when `name` is empty.
+    pub fn with_layer(self, name: &str, framebuffer: &Framebuffer) -> Self {
+        let channel = |f: fn(&Color) -> f64| {
+            framebuffer
+                .pixels()
+                .iter()
+                .map(|c| f(c) as f32)
+                .collect::<Vec<_>>()
+        };
+        let alpha = framebuffer.alpha().iter().map(|&a| a as f32).collect();
+        self.with_channel(&layer_channel(name, "R"), channel(Color::x))
+            .with_channel(&layer_channel(name, "G"), channel(Color::y))
+            .with_channel(&layer_channel(name, "B"), channel(Color::z))
+            .with_channel(&layer_channel(name, "A"), alpha)
+    }
+    /// `values` go row by row from the top, one per pixel.
+    pub fn with_channel(mut self, name: &str, values: Vec<f32>) -> Self {
+        assert_eq!(
+            values.len(),
+            (self.width * self.height) as usize,
+            "channel {} doesn't match the image size",
+            name
+        );
+        self.channels.push((name.to_string(), values));
+        self
+    }
+    pub fn write<P: AsRef<Path>>(&self, path: P, pixel_type: ExrPixelType) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer, pixel_type)?;
+        writer.flush()
+    }
+    // scanlines go out one at a time, only the header is put together in memory
+    fn write_to<W: Write>(&self, writer: &mut W, pixel_type: ExrPixelType) -> io::Result<()> {
+        // the format wants the channels sorted by name
+        let mut channels: Vec<&(String, Vec<f32>)> = self.channels.iter().collect();
+        channels.sort_by(|a, b| a.0.cmp(&b.0));
+        let (type_id, type_size) = match pixel_type {
+            ExrPixelType::Half => (1i32, 2),
+            ExrPixelType::Float => (2, 4),
+        };
+        let mut out = Vec::new();
+        out.extend(0x01312f76u32.to_le_bytes());
+        // version 2, single part scanlines, with the long names flag when needed
+        let long_names = channels.iter().any(|(name, _)| name.len() > 31);
+        out.extend((2u32 | if long_names { 0x400 } else { 0 }).to_le_bytes());
+
+        let mut chlist = Vec::new();
+        for (name, _) in &channels {
+            chlist.extend(name.as_bytes());
+            chlist.push(0);
+            chlist.extend(type_id.to_le_bytes());
+            // linear flag and three reserved bytes, then x and y sampling
+            chlist.extend([0; 4]);
+            chlist.extend(1i32.to_le_bytes());
+            chlist.extend(1i32.to_le_bytes());
+        }
+        chlist.push(0);
+        let window: Vec<u8> = [0, 0, self.width as i32 - 1, self.height as i32 - 1]
+            .iter()
+            .flat_map(|v: &i32| v.to_le_bytes())
+            .collect();
+        let attribute = |out: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]| {
+            out.extend(name.as_bytes());
+            out.push(0);
+            out.extend(kind.as_bytes());
+            out.push(0);
+            out.extend((value.len() as i32).to_le_bytes());
+            out.extend(value);
+        };
+        attribute(&mut out, "channels", "chlist", &chlist);
+        attribute(&mut out, "compression", "compression", &[0]);
+        attribute(&mut out, "dataWindow", "box2i", &window);
+        attribute(&mut out, "displayWindow", "box2i", &window);
+        attribute(&mut out, "lineOrder", "lineOrder", &[0]);
+        attribute(&mut out, "pixelAspectRatio", "float", &1f32.to_le_bytes());
+        attribute(&mut out, "screenWindowCenter", "v2f", &[0; 8]);
+        attribute(&mut out, "screenWindowWidth", "float", &1f32.to_le_bytes());
+        out.push(0);
+
+        // one scanline per chunk without compression, the offset table comes first
+        let line_size = self.width as usize * channels.len() * type_size;
+        let table_end = out.len() + self.height as usize * 8;
+        writer.write_all(&out)?;
+        for y in 0..self.height as usize {
+            let offset = table_end + y * (8 + line_size);
+            writer.write_all(&(offset as u64).to_le_bytes())?;
+        }
+        let mut line = Vec::with_capacity(8 + line_size);
+        for y in 0..self.height as usize {
+            line.clear();
+            line.extend((y as i32).to_le_bytes());
+            line.extend((line_size as i32).to_le_bytes());
+            for (_, values) in &channels {
+                let row = &values[y * self.width as usize..(y + 1) * self.width as usize];
+                for &value in row {
+                    match pixel_type {
+                        ExrPixelType::Half => line.extend(f32_to_half(value).to_le_bytes()),
+                        ExrPixelType::Float => line.extend(value.to_le_bytes()),
+                    }
+                }
+            }
+            writer.write_all(&line)?;
+        }
+        Ok(())
+    }
+}
+
+fn layer_channel(layer: &str, channel: &str) -> String {
+    if layer.is_empty() {
+        return channel.to_string();
+    }
+    format!("{}.{}", layer, channel)
+}
+
+/// nearest half precision float, ties to even. too large values become infinity.
+pub fn f32_to_half(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7fffff;
+    if exponent == 0xff {
+        let nan = if mantissa != 0 { 0x200 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    // round to nearest even, looking at the bits shifted out and the lowest bit kept
+    let round = |value: u32, shift: u32| {
+        let kept = value >> shift;
+        let half_way = 1 << (shift - 1);
+        if value & half_way != 0 && value & (3 * half_way - 1) != 0 {
+            kept + 1
+        } else {
+            kept
+        }
+    };
+    if half_exponent <= 0 {
+        if half_exponent < -10 {
+            return sign;
+        }
+        // subnormal, the implicit leading one becomes part of the mantissa
+        return sign | round(mantissa | 0x800000, (14 - half_exponent) as u32) as u16;
+    }
+    // a mantissa rounding up carries into the exponent, which is still right
+    sign | round(((half_exponent as u32) << 23) | mantissa, 13) as u16
+}
@@ -1,6 +1,6 @@
 
 
-use crate::{random_integer_with_range, Point3, Vec3};
+use crate::{Pcg32, Point3, Vec3};
 
 const POINT_COUNT: usize = u8::MAX as usize + 1;
 #[derive(Clone, Copy)]
@@ -12,33 +12,32 @@ pub struct Perlin {
         [usize; POINT_COUNT],
     ),
 }
-impl Default for Perlin {
-    fn default() -> Self {
+impl Perlin {
+    /// the same `rng` state always gives the same noise.
+    pub fn new(rng: &mut Pcg32) -> Self {
         let mut random_vec = [Vec3::default(); POINT_COUNT];
         for i in 0..POINT_COUNT {
-            random_vec[i] = Vec3::random_with_range(-1., 1.);
+            random_vec[i] = Vec3::random_with_range(-1., 1., rng);
         }
         let perm = (
-            Self::generate_perm(),
-            Self::generate_perm(),
-            Self::generate_perm(),
+            Self::generate_perm(rng),
+            Self::generate_perm(rng),
+            Self::generate_perm(rng),
         );
         Self { random_vec, perm }
     }
-}
-impl Perlin {
-    fn generate_perm() -> [usize; POINT_COUNT] {
+    fn generate_perm(rng: &mut Pcg32) -> [usize; POINT_COUNT] {
         let mut p = [0; POINT_COUNT];
         for i in 0..POINT_COUNT {
             p[i] = i;
         }
-        Self::permute(&mut p, POINT_COUNT);
+        Self::permute(&mut p, POINT_COUNT, rng);
         p
     }
 
-    fn permute(p: &mut [usize; POINT_COUNT], n: usize) {
+    fn permute(p: &mut [usize; POINT_COUNT], n: usize, rng: &mut Pcg32) {
         for i in 0..n {
-            let target = random_integer_with_range(0, i as u32) as usize;
+            let target = rng.random_integer_with_range(0, i as u32) as usize;
             let tmp = p[i];
             p[i] = p[target];
             p[target] = tmp;
@@ -1,14 +1,59 @@
-use super::{Color, Hittable, Point3, Vec3, INFINITY};
-#[derive(Default, Debug)]
+use std::cell::Cell;
+
+use super::{hash, Color, HitRecord, Hittable, Material, Point3, Sampler, Vec3, INFINITY};
+#[derive(Debug, Clone)]
 pub struct Ray {
     orig: Point3,
     dir: Vec3,
     tm: f64,
+    // kept next to the direction for the slab test, `sign` is 1 on axes it points down
+    inv_dir: Vec3,
+    sign: [usize; 3],
+    // uniform sample the next participating medium draws its free path from, moved on
+    // by every medium that takes it
+    medium_sample: Cell<f64>,
+}
+
+impl Default for Ray {
+    fn default() -> Self {
+        Self::new(Point3::default(), Vec3::default(), 0.)
+    }
 }
 
 impl Ray {
     pub fn new(orig: Point3, dir: Vec3, tm: f64) -> Self {
-        Self { orig, dir, tm }
+        let inv_dir = Vec3::new((1. / dir.x(), 1. / dir.y(), 1. / dir.z()));
+        let sign = [0, 1, 2].map(|a| (inv_dir[a] < 0.) as usize);
+        Self {
+            orig,
+            dir,
+            tm,
+            inv_dir,
+            sign,
+            medium_sample: Cell::new(0.5),
+        }
+    }
+    /// the uniform sample the first medium the ray passes through draws its free path
+    /// from. rays that never get one scatter at the median free path there.
+    pub fn with_medium_sample(mut self, u: f64) -> Self {
+        self.medium_sample = Cell::new(u);
+        self
+    }
+    pub fn medium_sample(&self) -> f64 {
+        self.medium_sample.get()
+    }
+    /// the medium sample, replaced by a hash of itself so the next medium along the ray
+    /// draws a free path independent of this one.
+    pub(crate) fn take_medium_sample(&self) -> f64 {
+        let u = self.medium_sample.get();
+        let bits = hash(&[u.to_bits()]);
+        self.medium_sample
+            .set((bits >> 11) as f64 / (1u64 << 53) as f64);
+        u
+    }
+    /// carries on from where the media `other` passed through left its sample.
+    pub(crate) fn follow_medium_sample(&self, other: &Ray) {
+        self.medium_sample.set(other.medium_sample());
     }
     pub fn orig(&self) -> &Point3 {
         &self.orig
@@ -19,8 +64,15 @@ impl Ray {
     pub fn direction(&self) -> &Vec3 {
         &self.dir
     }
-    pub fn direction_mut(&mut self) -> &mut Vec3 {
-        &mut self.dir
+    /// replaces the direction, which also updates the inverse direction and sign.
+    pub fn set_direction(&mut self, dir: Vec3) {
+        *self = Self::new(self.orig, dir, self.tm).with_medium_sample(self.medium_sample());
+    }
+    pub fn inv_direction(&self) -> &Vec3 {
+        &self.inv_dir
+    }
+    pub fn sign(&self) -> &[usize; 3] {
+        &self.sign
     }
     pub fn time(&self) -> f64 {
         self.tm
@@ -31,33 +83,160 @@ impl Ray {
     pub fn at(&self, t: f64) -> Point3 {
         self.orig + self.dir * t
     }
-    pub fn ray_color(&self, background: &Color, world: &dyn Hittable, depth: isize) -> Color {
+    pub fn ray_color(
+        &self,
+        background: &Color,
+        world: &dyn Hittable,
+        depth: isize,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
         if depth <= 0 {
             return Color::default();
         }
-        let rec = world.hit(self, 0.001, INFINITY);
+        let rec = world.hit(
+            &self.clone().with_medium_sample(sampler.get_1d()),
+            0.001,
+            INFINITY,
+        );
         match rec {
-            Some(mut rec) => {
-                let mut scattered = Ray::default();
-                let mut attention = Color::default();
-                let emitted = rec
-                    .mat_ptr
-                    .clone()
-                    .unwrap()
-                    .emitted(rec.u, rec.v, &rec.p);
-                if !rec.mat_ptr.clone().unwrap().as_ref().scatter(
-                    self,
-                    &mut rec,
-                    &mut attention,
-                    &mut scattered,
-                ) {
-                    return emitted;
+            Some(rec) => {
+                let mat = rec.mat_ptr.clone().unwrap();
+                let emitted = mat.emitted(rec.u, rec.v, &rec.p);
+                match mat.scatter(self, &rec, sampler) {
+                    Some(srec) => {
+                        emitted
+                            + srec.attenuation
+                                * srec
+                                    .scattered
+                                    .ray_color(background, world, depth - 1, sampler)
+                    }
+                    None => emitted,
                 }
-                emitted + attention * scattered.ray_color(background, world, depth - 1)
             }
-            None => {
-                *background
+            None => *background,
+        }
+    }
+    /// same as `ray_color`, but every non specular bounce also samples a point on `lights`, and both estimates are weighted with `heuristic`.
+    pub fn ray_color_with_lights(
+        &self,
+        background: &Color,
+        world: &dyn Hittable,
+        lights: &dyn Hittable,
+        heuristic: MisHeuristic,
+        depth: isize,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        let scene = LightSampledScene {
+            background,
+            world,
+            lights,
+            heuristic,
+        };
+        self.light_sampled_color(&scene, depth, 1., sampler)
+    }
+    fn light_sampled_color(
+        &self,
+        scene: &LightSampledScene,
+        depth: isize,
+        emission_weight: f64,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        if depth <= 0 {
+            return Color::default();
+        }
+        let ray = self.clone().with_medium_sample(sampler.get_1d());
+        let rec = match scene.world.hit(&ray, 0.001, INFINITY) {
+            Some(rec) => rec,
+            None => return *scene.background,
+        };
+        let mat = rec.mat_ptr.clone().unwrap();
+        let emitted = emission_weight * mat.emitted(rec.u, rec.v, &rec.p);
+        let srec = mat.scatter(self, &rec, sampler);
+        if let Some(srec) = srec.as_ref().filter(|srec| srec.is_specular) {
+            return emitted
+                + srec.attenuation
+                    * srec
+                        .scattered
+                        .light_sampled_color(scene, depth - 1, 1., sampler);
+        }
+        // a material sample that failed only loses the indirect bounce, the lights are
+        // still sampled, materials that can't scatter at all give a `pdf` of 0 for them
+        let direct = self.sample_lights(scene, &rec, mat.as_ref(), sampler);
+        let srec = match srec {
+            Some(srec) => srec,
+            None => return emitted + direct,
+        };
+        let light_pdf = scene.lights.pdf_value(&rec.p, srec.scattered.direction());
+        emitted
+            + direct
+            + srec.attenuation
+                * srec.scattered.light_sampled_color(
+                    scene,
+                    depth - 1,
+                    scene.heuristic.weight(srec.pdf, light_pdf),
+                    sampler,
+                )
+    }
+    fn sample_lights(
+        &self,
+        scene: &LightSampledScene,
+        rec: &HitRecord,
+        mat: &dyn Material,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        let to_light = scene.lights.random(&rec.p, sampler);
+        let light_pdf = scene.lights.pdf_value(&rec.p, &to_light);
+        if light_pdf <= 0. {
+            return Color::default();
+        }
+        let wo = -self.dir;
+        let scattering_pdf = mat.pdf(rec, &to_light, &wo);
+        if scattering_pdf <= 0. {
+            return Color::default();
+        }
+        let shadow_ray =
+            Ray::new(rec.p, to_light, self.time()).with_medium_sample(sampler.get_1d());
+        match scene.world.hit(&shadow_ray, 0.001, INFINITY) {
+            Some(light_rec) => {
+                let emitted =
+                    light_rec
+                        .mat_ptr
+                        .unwrap()
+                        .emitted(light_rec.u, light_rec.v, &light_rec.p);
+                let weight = scene.heuristic.weight(light_pdf, scattering_pdf);
+                weight * mat.eval(rec, &to_light, &wo) * emitted / light_pdf
             }
+            None => Color::default(),
+        }
+    }
+}
+
+// what stays the same along a light sampled path
+struct LightSampledScene<'a> {
+    background: &'a Color,
+    world: &'a dyn Hittable,
+    lights: &'a dyn Hittable,
+    heuristic: MisHeuristic,
+}
+
+/// how light sampling and material sampling share a path's emitted light.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MisHeuristic {
+    Balance,
+    #[default]
+    Power,
+}
+
+impl MisHeuristic {
+    /// weight of the strategy that sampled with `pdf` against the one with `other_pdf`.
+    pub fn weight(&self, pdf: f64, other_pdf: f64) -> f64 {
+        let (f, g) = match self {
+            MisHeuristic::Balance => (pdf, other_pdf),
+            MisHeuristic::Power => (pdf * pdf, other_pdf * other_pdf),
+        };
+        if f + g <= 0. {
+            return 0.;
         }
+        f / (f + g)
     }
 }
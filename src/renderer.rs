@@ -0,0 +1,480 @@
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use crate::{
+    hash, load_checkpoint, save_checkpoint, AdaptiveSampling, Aovs, Camera, CheckpointError, Color,
+    Framebuffer, Hittable, IndependentSampler, MisHeuristic, PixelAovs, PixelEstimate, Ray,
+    SampleMap, Sampler, SamplerKind, AABB, INFINITY,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct RenderSettings {
+    /// at least 2, the outermost pixels sit on the edges of the camera's view.
+    pub image_width: u32,
+    /// at least 2, like the width.
+    pub image_height: u32,
+    /// the most samples a pixel takes when sampling adaptively.
+    pub samples_per_pixel: usize,
+    /// stop sampling pixels early once they are smooth enough.
+    pub adaptive: Option<AdaptiveSampling>,
+    pub max_depth: isize,
+    /// worker count, 0 means one worker per available core.
+    pub threads: usize,
+    pub tile_size: u32,
+    /// only used when the renderer has lights to sample.
+    pub mis_heuristic: MisHeuristic,
+    pub sampler: SamplerKind,
+    /// samples only depend on this seed and their pixel, so the same seed gives the
+    /// same image whatever the thread count and tile order.
+    pub seed: u64,
+    /// called with the number of tiles left each time a worker starts on a tile.
+    pub progress: Option<fn(usize)>,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            image_width: 400,
+            image_height: 225,
+            samples_per_pixel: 100,
+            adaptive: None,
+            max_depth: 50,
+            threads: 0,
+            tile_size: 16,
+            mis_heuristic: MisHeuristic::default(),
+            sampler: SamplerKind::default(),
+            seed: 0,
+            progress: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Tile {
+    x: (u32, u32),
+    y: (u32, u32),
+}
+
+pub struct Renderer {
+    camera: Camera,
+    world: Arc<dyn Hittable>,
+    lights: Option<Arc<dyn Hittable>>,
+    background: Color,
+    settings: RenderSettings,
+    scene_key: u64,
+}
+
+// camera rays per side of the grid `checkpoint_hash` probes the scene with
+const PROBE_GRID: u32 = 16;
+
+impl Renderer {
+    /// panics when the image is narrower or lower than 2 pixels.
+    pub fn new(
+        camera: Camera,
+        world: Arc<dyn Hittable>,
+        background: Color,
+        settings: RenderSettings,
+    ) -> Self {
+        assert!(
+            settings.image_width >= 2 && settings.image_height >= 2,
+            "image must be at least 2 by 2 pixels, not {} by {}",
+            settings.image_width,
+            settings.image_height
+        );
+        Self {
+            camera,
+            world,
+            lights: None,
+            background,
+            settings,
+            scene_key: 0,
+        }
+    }
+    /// emitters to sample directly at every diffuse bounce, they must also be part of the world.
+    pub fn with_lights(mut self, lights: Arc<dyn Hittable>) -> Self {
+        self.lights = Some(lights);
+        self
+    }
+    /// goes into `checkpoint_hash` as it is, for telling apart scenes the hash can't,
+    /// like ones differing only in a metal's fuzz or in something the camera doesn't see.
+    pub fn with_scene_key(mut self, key: u64) -> Self {
+        self.scene_key = key;
+        self
+    }
+    pub fn settings(&self) -> &RenderSettings {
+        &self.settings
+    }
+    pub fn render(&self) -> Framebuffer {
+        self.render_with_sample_map().0
+    }
+    /// also gives how many samples each pixel took.
+    pub fn render_with_sample_map(&self) -> (Framebuffer, SampleMap) {
+        let mut accumulator = self.accumulator();
+        self.render_pass(&mut accumulator, self.settings.samples_per_pixel);
+        (accumulator.to_framebuffer(), accumulator.sample_map())
+    }
+    /// renders the whole frame in passes of `samples_per_pass` and hands the result so far
+    /// to `on_pass` after each one, until every pixel has all its samples. ends up with
+    /// the same image as `render`.
+    pub fn render_progressive<F>(&self, samples_per_pass: usize, on_pass: F) -> Accumulator
+    where
+        F: FnMut(usize, &Accumulator),
+    {
+        self.resume_progressive(self.accumulator(), samples_per_pass, on_pass)
+    }
+    /// `render_progressive` carrying on from `accumulator`, usually a loaded checkpoint.
+    /// passes are counted from this call on.
+    pub fn resume_progressive<F>(
+        &self,
+        mut accumulator: Accumulator,
+        samples_per_pass: usize,
+        mut on_pass: F,
+    ) -> Accumulator
+    where
+        F: FnMut(usize, &Accumulator),
+    {
+        let mut pass = 0;
+        while !self.is_finished(&accumulator) {
+            self.render_pass(&mut accumulator, samples_per_pass.max(1));
+            pass += 1;
+            on_pass(pass, &accumulator);
+        }
+        accumulator
+    }
+    /// saves the progress so far, see `save_checkpoint`.
+    pub fn save_checkpoint<P: AsRef<Path>>(
+        &self,
+        accumulator: &Accumulator,
+        path: P,
+    ) -> Result<(), CheckpointError> {
+        save_checkpoint(
+            path,
+            self.checkpoint_hash(),
+            self.settings.seed,
+            accumulator,
+        )
+    }
+    /// loads the progress saved by `save_checkpoint`, as long as it was rendered with the
+    /// same settings and a scene that looks the same.
+    pub fn load_checkpoint<P: AsRef<Path>>(&self, path: P) -> Result<Accumulator, CheckpointError> {
+        load_checkpoint(path, self.checkpoint_hash())
+    }
+    /// hash of everything that decides the image, threads and tiles don't. the scene
+    /// can't be hashed as a whole, so it stands in with its bounds and what a grid of
+    /// camera rays hits first: where, facing which way, and the albedo and emission there.
+    /// edits none of those rays see go unnoticed unless the scene key changes with them.
+    pub fn checkpoint_hash(&self) -> u64 {
+        let settings = &self.settings;
+        let adaptive = settings.adaptive.map_or([0, 0], |a| {
+            [a.min_samples as u64 + 1, a.threshold.to_bits()]
+        });
+        let mut bounds = AABB::default();
+        let bounded = self.world.bounding_box((0., 1.), &mut bounds);
+        let (min, max) = (bounds.min(), bounds.max());
+        hash(&[
+            settings.image_width as u64,
+            settings.image_height as u64,
+            settings.samples_per_pixel as u64,
+            adaptive[0],
+            adaptive[1],
+            settings.max_depth as u64,
+            settings.mis_heuristic as u64,
+            settings.sampler as u64,
+            settings.seed,
+            self.lights.is_some() as u64,
+            self.camera.hash(),
+            self.background.x().to_bits(),
+            self.background.y().to_bits(),
+            self.background.z().to_bits(),
+            bounded as u64,
+            min.x().to_bits(),
+            min.y().to_bits(),
+            min.z().to_bits(),
+            max.x().to_bits(),
+            max.y().to_bits(),
+            max.z().to_bits(),
+            self.probe_hash(),
+            self.scene_key,
+        ])
+    }
+    fn probe_hash(&self) -> u64 {
+        // a fixed sampler, so the probes go through the same spots of the lens every time
+        let mut sampler = IndependentSampler::new(0);
+        let mut values = Vec::new();
+        for i in 0..PROBE_GRID {
+            for j in 0..PROBE_GRID {
+                let u = (i as f64 + 0.5) / PROBE_GRID as f64;
+                let v = (j as f64 + 0.5) / PROBE_GRID as f64;
+                sampler.start_sample((i, j), 0);
+                let ray = self.camera.get_ray(u, v, &mut sampler);
+                let rec = match self.world.hit(&ray, 0.001, INFINITY) {
+                    Some(rec) => rec,
+                    None => {
+                        values.push(0);
+                        continue;
+                    }
+                };
+                let (albedo, emitted) = match &rec.mat_ptr {
+                    Some(mat) => (mat.albedo(&rec), mat.emitted(rec.u, rec.v, &rec.p)),
+                    None => (Color::default(), Color::default()),
+                };
+                values.push(rec.t.to_bits());
+                for vector in [rec.normal, albedo, emitted] {
+                    values.extend([vector.x(), vector.y(), vector.z()].map(f64::to_bits));
+                }
+            }
+        }
+        hash(&values)
+    }
+    /// an empty accumulator for this renderer's image size.
+    pub fn accumulator(&self) -> Accumulator {
+        Accumulator::new(self.settings.image_width, self.settings.image_height)
+    }
+    /// adds up to `samples` more samples to every pixel that still needs them.
+    pub fn render_pass(&self, accumulator: &mut Accumulator, samples: usize) {
+        let shared = &*accumulator;
+        let finished = self.render_tiles(|tile| self.render_tile(tile, shared, samples));
+        for (tile, pixels) in finished {
+            let mut pixels = pixels.into_iter();
+            for y in tile.y.0..tile.y.1 {
+                for x in tile.x.0..tile.x.1 {
+                    accumulator.put(x, y, pixels.next().unwrap());
+                }
+            }
+        }
+    }
+    /// the first hits of `samples` camera rays per pixel, the same rays the first samples
+    /// of `render` start with.
+    pub fn render_aovs(&self, samples: usize) -> Aovs {
+        let (width, height) = (self.settings.image_width, self.settings.image_height);
+        let mut pixels = vec![PixelAovs::default(); (width * height) as usize];
+        for (tile, tile_pixels) in self.render_tiles(|tile| self.render_aov_tile(tile, samples)) {
+            let mut tile_pixels = tile_pixels.into_iter();
+            for y in tile.y.0..tile.y.1 {
+                for x in tile.x.0..tile.x.1 {
+                    pixels[(y * width + x) as usize] = tile_pixels.next().unwrap();
+                }
+            }
+        }
+        Aovs::from_pixels(width, height, &pixels)
+    }
+    pub fn is_finished(&self, accumulator: &Accumulator) -> bool {
+        accumulator
+            .estimates()
+            .iter()
+            .all(|estimate| self.is_pixel_finished(estimate))
+    }
+    fn is_pixel_finished(&self, estimate: &PixelEstimate) -> bool {
+        estimate.count() >= self.settings.samples_per_pixel
+            || self
+                .settings
+                .adaptive
+                .is_some_and(|adaptive| adaptive.converged(estimate))
+    }
+    fn thread_count(&self) -> usize {
+        match self.settings.threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        }
+    }
+    fn tiles(&self) -> Vec<Tile> {
+        let (width, height) = (self.settings.image_width, self.settings.image_height);
+        let size = self.settings.tile_size.max(1);
+        let mut tiles = Vec::new();
+        for y in (0..height).step_by(size as usize) {
+            for x in (0..width).step_by(size as usize) {
+                tiles.push(Tile {
+                    x: (x, (x + size).min(width)),
+                    y: (y, (y + size).min(height)),
+                });
+            }
+        }
+        tiles
+    }
+    // hands the tiles out to the workers, each one runs `render_tile` on them
+    fn render_tiles<T, F>(&self, render_tile: F) -> Vec<(Tile, Vec<T>)>
+    where
+        T: Send,
+        F: Fn(&Tile) -> Vec<T> + Sync,
+    {
+        let tiles = self.tiles();
+        let next_tile = AtomicUsize::new(0);
+        thread::scope(|s| {
+            let workers: Vec<_> = (0..self.thread_count())
+                .map(|_| {
+                    s.spawn(|| {
+                        let mut done = Vec::new();
+                        loop {
+                            let index = next_tile.fetch_add(1, Ordering::Relaxed);
+                            if index >= tiles.len() {
+                                break;
+                            }
+                            if let Some(progress) = self.settings.progress {
+                                progress(tiles.len() - index);
+                            }
+                            let tile = tiles[index];
+                            done.push((tile, render_tile(&tile)));
+                        }
+                        done
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect()
+        })
+    }
+    fn render_tile(
+        &self,
+        tile: &Tile,
+        accumulator: &Accumulator,
+        samples: usize,
+    ) -> Vec<PixelEstimate> {
+        let mut sampler = self
+            .settings
+            .sampler
+            .create(self.settings.samples_per_pixel, self.settings.seed);
+        let mut pixels = Vec::new();
+        for y in tile.y.0..tile.y.1 {
+            for x in tile.x.0..tile.x.1 {
+                let mut estimate = *accumulator.get(x, y);
+                self.render_pixel(x, y, &mut estimate, samples, sampler.as_mut());
+                pixels.push(estimate);
+            }
+        }
+        pixels
+    }
+    // sample indices carry on from the samples the pixel already has, so splitting the
+    // samples into passes doesn't change them
+    fn render_pixel(
+        &self,
+        x: u32,
+        y: u32,
+        estimate: &mut PixelEstimate,
+        samples: usize,
+        sampler: &mut dyn Sampler,
+    ) {
+        for _ in 0..samples {
+            if self.is_pixel_finished(estimate) {
+                break;
+            }
+            estimate.add(self.render_sample(x, y, estimate.count(), sampler));
+        }
+    }
+    fn render_aov_tile(&self, tile: &Tile, samples: usize) -> Vec<PixelAovs> {
+        let mut sampler = self
+            .settings
+            .sampler
+            .create(self.settings.samples_per_pixel, self.settings.seed);
+        let mut pixels = Vec::new();
+        for y in tile.y.0..tile.y.1 {
+            for x in tile.x.0..tile.x.1 {
+                let mut pixel = PixelAovs::default();
+                for index in 0..samples {
+                    // the medium sample is drawn where `ray_color` draws it
+                    let r = self
+                        .camera_ray(x, y, index, sampler.as_mut())
+                        .with_medium_sample(sampler.get_1d());
+                    pixel.add(&r, self.world.hit(&r, 0.001, INFINITY).as_ref());
+                }
+                pixels.push(pixel);
+            }
+        }
+        pixels
+    }
+    fn render_sample(&self, x: u32, y: u32, index: usize, sampler: &mut dyn Sampler) -> Color {
+        let r = self.camera_ray(x, y, index, sampler);
+        match &self.lights {
+            Some(lights) => r.ray_color_with_lights(
+                &self.background,
+                self.world.as_ref(),
+                lights.as_ref(),
+                self.settings.mis_heuristic,
+                self.settings.max_depth,
+                sampler,
+            ),
+            None => r.ray_color(
+                &self.background,
+                self.world.as_ref(),
+                self.settings.max_depth,
+                sampler,
+            ),
+        }
+    }
+    fn camera_ray(&self, x: u32, y: u32, index: usize, sampler: &mut dyn Sampler) -> Ray {
+        let (width, height) = (self.settings.image_width, self.settings.image_height);
+        // framebuffer rows go top down while the camera's v goes bottom up.
+        let row = (height - 1 - y) as f64;
+        sampler.start_sample((x, y), index as u32);
+        let jitter = sampler.get_2d();
+        let u = (x as f64 + jitter.0) / (width - 1) as f64;
+        let v = (row + jitter.1) / (height - 1) as f64;
+        self.camera.get_ray(u, v, sampler)
+    }
+}
+
+/// the estimates of every pixel of a render in progress, row 0 is the top of the picture.
+#[derive(Clone, Debug)]
+pub struct Accumulator {
+    width: u32,
+    height: u32,
+    estimates: Vec<PixelEstimate>,
+}
+
+impl Accumulator {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            estimates: vec![PixelEstimate::default(); (width * height) as usize],
+        }
+    }
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    pub fn estimates(&self) -> &[PixelEstimate] {
+        &self.estimates
+    }
+    pub fn get(&self, x: u32, y: u32) -> &PixelEstimate {
+        &self.estimates[(y * self.width + x) as usize]
+    }
+    pub fn put(&mut self, x: u32, y: u32, estimate: PixelEstimate) {
+        self.estimates[(y * self.width + x) as usize] = estimate;
+    }
+    pub fn to_framebuffer(&self) -> Framebuffer {
+        let mut framebuffer = Framebuffer::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                framebuffer.put_pixel(x, y, self.get(x, y).color());
+            }
+        }
+        framebuffer
+    }
+    /// the variance of each pixel's mean luminance, what `Denoiser` needs to know how noisy
+    /// the pixels are.
+    pub fn variances(&self) -> Vec<f64> {
+        self.estimates
+            .iter()
+            .map(|estimate| estimate.variance() / estimate.count().max(1) as f64)
+            .collect()
+    }
+    pub fn sample_map(&self) -> SampleMap {
+        let mut sample_map = SampleMap::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                sample_map.put(x, y, self.get(x, y).count());
+            }
+        }
+        sample_map
+    }
+}
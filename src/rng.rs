@@ -0,0 +1,61 @@
+/// pcg32 random number generator (O'Neill 2014). every `stream` of a seed is an
+/// independent sequence, so each pixel can get its own and a render comes out the same
+/// whichever thread picks the pixel up.
+#[derive(Clone, Debug)]
+pub struct Pcg32 {
+    state: u64,
+    inc: u64,
+}
+
+const MULTIPLIER: u64 = 6364136223846793005;
+
+impl Default for Pcg32 {
+    fn default() -> Self {
+        Self::new(0, 0)
+    }
+}
+
+impl Pcg32 {
+    pub fn new(seed: u64, stream: u64) -> Self {
+        let mut rng = Self {
+            state: 0,
+            inc: (stream << 1) | 1,
+        };
+        rng.next_u32();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.next_u32();
+        rng
+    }
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(MULTIPLIER).wrapping_add(self.inc);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+    /// uniform in [0, 1).
+    pub fn random_float(&mut self) -> f64 {
+        self.next_u32() as f64 / (u32::MAX as f64 + 1.)
+    }
+    pub fn random_float_with_range(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.random_float()
+    }
+    /// uniform in `min..=max`.
+    pub fn random_integer_with_range(&mut self, min: u32, max: u32) -> u32 {
+        let span = (max - min) as u64 + 1;
+        min + ((self.next_u32() as u64 * span) >> 32) as u32
+    }
+}
+
+/// splitmix64 finalizer, scatters nearby inputs across all 64 bits.
+pub(crate) fn mix_bits(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// hash of a few words, for seeding things that have no rng of their own.
+pub(crate) fn hash(values: &[u64]) -> u64 {
+    values.iter().fold(0x9e3779b97f4a7c15, |h, &v| {
+        mix_bits((h ^ v).wrapping_add(0x9e3779b97f4a7c15))
+    })
+}
@@ -0,0 +1,321 @@
+use crate::{hash, Pcg32};
+
+/// source of the sample values one camera sample consumes: pixel jitter, lens, time,
+/// then the bounces. each call takes the next dimension, `start_sample` goes back to
+/// the first one.
+///
+/// every value depends only on the seed, the pixel, the sample index and the dimension,
+/// so samples can be taken in any order and on any thread.
+pub trait Sampler: Send {
+    fn start_sample(&mut self, pixel: (u32, u32), index: u32);
+    /// uniform in [0, 1).
+    fn get_1d(&mut self) -> f64;
+    /// uniform in [0, 1)².
+    fn get_2d(&mut self) -> (f64, f64);
+}
+
+/// which `Sampler` a render uses.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SamplerKind {
+    /// plain white noise.
+    Independent,
+    /// jittered strata, laid out for `samples_per_pixel` samples.
+    Stratified,
+    /// digit scrambled halton sequence, white noise past the first 64 dimensions.
+    Halton,
+    /// owen scrambled sobol points, each dimension pair shuffled on its own.
+    #[default]
+    Sobol,
+}
+
+impl SamplerKind {
+    pub fn create(&self, samples_per_pixel: usize, seed: u64) -> Box<dyn Sampler> {
+        match self {
+            SamplerKind::Independent => Box::new(IndependentSampler::new(seed)),
+            SamplerKind::Stratified => Box::new(StratifiedSampler::new(samples_per_pixel, seed)),
+            SamplerKind::Halton => Box::new(HaltonSampler::new(seed)),
+            SamplerKind::Sobol => Box::new(SobolSampler::new(seed)),
+        }
+    }
+}
+
+// largest f64 below 1
+const ONE_MINUS_EPSILON: f64 = 1. - f64::EPSILON / 2.;
+
+// where the sampler stands, shared by all of them
+#[derive(Clone, Debug, Default)]
+struct SampleState {
+    seed: u64,
+    pixel_seed: u64,
+    index: u32,
+    dimension: u32,
+    rng: Pcg32,
+}
+
+impl SampleState {
+    fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            ..Default::default()
+        }
+    }
+    fn start(&mut self, pixel: (u32, u32), index: u32) {
+        self.pixel_seed = hash(&[self.seed, pixel.0 as u64, pixel.1 as u64]);
+        self.index = index;
+        self.dimension = 0;
+        self.rng = Pcg32::new(self.pixel_seed, index as u64);
+    }
+    // takes `count` dimensions and returns the first
+    fn next_dimension(&mut self, count: u32) -> u32 {
+        let dimension = self.dimension;
+        self.dimension += count;
+        dimension
+    }
+    fn dimension_hash(&self, dimension: u32) -> u64 {
+        hash(&[self.pixel_seed, dimension as u64])
+    }
+}
+
+pub struct IndependentSampler {
+    state: SampleState,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: SampleState::new(seed),
+        }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn start_sample(&mut self, pixel: (u32, u32), index: u32) {
+        self.state.start(pixel, index);
+    }
+    fn get_1d(&mut self) -> f64 {
+        self.state.rng.random_float()
+    }
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.state.rng.random_float(), self.state.rng.random_float())
+    }
+}
+
+/// every dimension of a pixel is split into `samples_per_pixel` strata (a square grid
+/// of about as many cells for 2d), and each sample lands in its own stratum, in a
+/// different order per dimension. past `samples_per_pixel` the strata are handed out
+/// again in a new order.
+pub struct StratifiedSampler {
+    state: SampleState,
+    strata: u32,
+    grid: u32,
+}
+
+impl StratifiedSampler {
+    pub fn new(samples_per_pixel: usize, seed: u64) -> Self {
+        let strata = samples_per_pixel.max(1) as u32;
+        Self {
+            state: SampleState::new(seed),
+            strata,
+            grid: (strata as f64).sqrt().floor() as u32,
+        }
+    }
+    // this sample's stratum out of `count`, the same index never gets the same one twice
+    // within a round
+    fn stratum(&self, dimension: u32, count: u32) -> u32 {
+        let round = self.state.index / count;
+        let seed = hash(&[self.state.dimension_hash(dimension), round as u64]) as u32;
+        permutation_element(self.state.index % count, count, seed)
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_sample(&mut self, pixel: (u32, u32), index: u32) {
+        self.state.start(pixel, index);
+    }
+    fn get_1d(&mut self) -> f64 {
+        let dimension = self.state.next_dimension(1);
+        let stratum = self.stratum(dimension, self.strata);
+        let jitter = self.state.rng.random_float();
+        ((stratum as f64 + jitter) / self.strata as f64).min(ONE_MINUS_EPSILON)
+    }
+    fn get_2d(&mut self) -> (f64, f64) {
+        let dimension = self.state.next_dimension(2);
+        let stratum = self.stratum(dimension, self.grid * self.grid);
+        let (x, y) = (stratum % self.grid, stratum / self.grid);
+        let jitter = (self.state.rng.random_float(), self.state.rng.random_float());
+        (
+            ((x as f64 + jitter.0) / self.grid as f64).min(ONE_MINUS_EPSILON),
+            ((y as f64 + jitter.1) / self.grid as f64).min(ONE_MINUS_EPSILON),
+        )
+    }
+}
+
+const PRIMES: [u64; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89,
+    97, 101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191,
+    193, 197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293,
+    307, 311,
+];
+
+/// dimension `i` is the radical inverse of the sample index in the `i`th prime, with
+/// its digits randomly permuted per pixel so neighbouring pixels don't share the same
+/// points.
+pub struct HaltonSampler {
+    state: SampleState,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: SampleState::new(seed),
+        }
+    }
+    fn sample(&mut self, dimension: u32) -> f64 {
+        match PRIMES.get(dimension as usize) {
+            Some(&base) => scrambled_radical_inverse(
+                base,
+                self.state.index as u64,
+                self.state.dimension_hash(dimension),
+            ),
+            None => self.state.rng.random_float(),
+        }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_sample(&mut self, pixel: (u32, u32), index: u32) {
+        self.state.start(pixel, index);
+    }
+    fn get_1d(&mut self) -> f64 {
+        let dimension = self.state.next_dimension(1);
+        self.sample(dimension)
+    }
+    fn get_2d(&mut self) -> (f64, f64) {
+        let dimension = self.state.next_dimension(2);
+        (self.sample(dimension), self.sample(dimension + 1))
+    }
+}
+
+fn scrambled_radical_inverse(base: u64, mut a: u64, seed: u64) -> f64 {
+    let inv_base = 1. / base as f64;
+    let (mut x, mut inv_base_n, mut position) = (0., inv_base, 0);
+    // the zeros past the last digit of `a` are permuted too, down to the last bit of x
+    while inv_base_n > f64::EPSILON {
+        let permutation = hash(&[seed, position]) as u32;
+        let digit = permutation_element((a % base) as u32, base as u32, permutation);
+        x += digit as f64 * inv_base_n;
+        inv_base_n *= inv_base;
+        a /= base;
+        position += 1;
+    }
+    x.min(ONE_MINUS_EPSILON)
+}
+
+/// the first two sobol dimensions, used for every 1d and 2d request. the sample index is
+/// shuffled with a different nested uniform scramble per request and the points are owen
+/// scrambled, so the dimensions stay uncorrelated while every power of two prefix of a
+/// pixel's samples stays stratified (Burley 2020).
+pub struct SobolSampler {
+    state: SampleState,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: SampleState::new(seed),
+        }
+    }
+    fn shuffled_index(&self, seed: u64) -> u32 {
+        nested_uniform_scramble(self.state.index, seed as u32)
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_sample(&mut self, pixel: (u32, u32), index: u32) {
+        self.state.start(pixel, index);
+    }
+    fn get_1d(&mut self) -> f64 {
+        let dimension = self.state.next_dimension(1);
+        let seed = self.state.dimension_hash(dimension);
+        // the first sobol dimension is the van der corput sequence
+        let x = self.shuffled_index(seed).reverse_bits();
+        to_unit_float(nested_uniform_scramble(x, (seed >> 32) as u32))
+    }
+    fn get_2d(&mut self) -> (f64, f64) {
+        let dimension = self.state.next_dimension(2);
+        let seed = self.state.dimension_hash(dimension);
+        let index = self.shuffled_index(seed);
+        let scramble = hash(&[seed]);
+        (
+            to_unit_float(nested_uniform_scramble(
+                index.reverse_bits(),
+                scramble as u32,
+            )),
+            to_unit_float(nested_uniform_scramble(
+                second_sobol(index),
+                (scramble >> 32) as u32,
+            )),
+        )
+    }
+}
+
+fn to_unit_float(x: u32) -> f64 {
+    x as f64 / (u32::MAX as f64 + 1.)
+}
+
+// direction numbers of the second dimension follow v_k = v_(k-1) ^ (v_(k-1) >> 1)
+fn second_sobol(mut index: u32) -> u32 {
+    let (mut v, mut x) = (1u32 << 31, 0);
+    while index != 0 {
+        if index & 1 == 1 {
+            x ^= v;
+        }
+        index >>= 1;
+        v ^= v >> 1;
+    }
+    x
+}
+
+// Laine and Karras' hash, an owen scramble of the reversed bits
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    let mut x = x.reverse_bits().wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x.reverse_bits()
+}
+
+// element `i` of a random permutation of 0..len picked by `seed` (Kensler 2013)
+fn permutation_element(mut i: u32, len: u32, seed: u32) -> u32 {
+    let mut w = len - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= seed >> 16;
+        i ^= (i & w) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= seed >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < len {
+            break;
+        }
+    }
+    i.wrapping_add(seed) % len
+}
@@ -1,8 +1,8 @@
-use std::sync::Arc;
+use std::{path::Path, sync::Arc};
 
 use image::RgbImage;
 
-use crate::{Color, Perlin, Point3};
+use crate::{Color, Pcg32, Perlin, Point3};
 
 pub trait Texture : Send + Sync{
     fn value(&self, u: f64, v: f64, p: &Point3) -> Color;
@@ -55,7 +55,7 @@ impl CheckerTexture {
         Self { odd, even }
     }
 }
-#[derive(Default, Clone, Copy)]
+#[derive(Clone, Copy)]
 pub struct NoiseTexture {
     pub(crate) noise: Perlin,
     pub(crate) scale: f64,
@@ -67,10 +67,10 @@ impl Texture for NoiseTexture {
     }
 }
 impl NoiseTexture {
-    pub fn new(scale: f64) -> Self {
+    pub fn new(scale: f64, rng: &mut Pcg32) -> Self {
         Self {
+            noise: Perlin::new(rng),
             scale,
-            ..Default::default()
         }
     }
 }
@@ -103,12 +103,14 @@ impl Texture for ImageTexture {
 
 impl ImageTexture {
     pub fn new(filename: &str) -> Self {
-        match image::open(filename){
-            Ok(img) => return Self { 
-                data: img.to_rgb8()
-            },
-            Err(err) => panic!("{:?}",err),
-        };
-        
+        match Self::open(filename) {
+            Ok(texture) => texture,
+            Err(err) => panic!("{:?}", err),
+        }
+    }
+    pub fn open<P: AsRef<Path>>(filename: P) -> Result<Self, image::ImageError> {
+        Ok(Self {
+            data: image::open(filename)?.to_rgb8(),
+        })
     }
 }
\ No newline at end of file
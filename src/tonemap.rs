@@ -0,0 +1,105 @@
+use image::RgbImage;
+
+use crate::{luminance, Color, Framebuffer};
+
+/// curve squeezing scene radiance into the displayable [0, 1].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ToneMapOperator {
+    /// no curve, everything above 1 clips.
+    Clamp,
+    /// `l / (1 + l)` on the luminance, so hues are kept.
+    Reinhard,
+    /// Reinhard reaching 1 at luminance `white` instead of at infinity.
+    ExtendedReinhard { white: f64 },
+    /// John Hable's filmic curve from Uncharted 2.
+    Hable,
+    /// Krzysztof Narkowicz's fit of the ACES filmic curve.
+    #[default]
+    Aces,
+}
+
+/// exposure and a tone curve, taking linear radiance to linear display values.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ToneMap {
+    /// in stops, each one doubles the brightness.
+    pub(crate) exposure: f64,
+    pub(crate) operator: ToneMapOperator,
+}
+
+impl ToneMap {
+    pub fn new(operator: ToneMapOperator) -> Self {
+        Self {
+            exposure: 0.,
+            operator,
+        }
+    }
+    pub fn with_exposure(mut self, stops: f64) -> Self {
+        self.exposure = stops;
+        self
+    }
+    pub fn apply(&self, c: &Color) -> Color {
+        let c = *c * 2f64.powf(self.exposure);
+        let mapped = match self.operator {
+            ToneMapOperator::Clamp => c,
+            ToneMapOperator::Reinhard => scale_luminance(&c, |l| l / (1. + l)),
+            ToneMapOperator::ExtendedReinhard { white } => {
+                scale_luminance(&c, |l| l * (1. + l / (white * white)) / (1. + l))
+            }
+            ToneMapOperator::Hable => per_channel(&c, |x| {
+                // the curve works on twice the exposure and is normalized to white at 11.2
+                const WHITE: f64 = 11.2;
+                hable_partial(2. * x) / hable_partial(WHITE)
+            }),
+            ToneMapOperator::Aces => per_channel(&c, |x| {
+                let x = 0.6 * x;
+                x * (2.51 * x + 0.03) / (x * (2.43 * x + 0.59) + 0.14)
+            }),
+        };
+        per_channel(&mapped, |x| x.clamp(0., 1.))
+    }
+}
+
+fn per_channel<F: Fn(f64) -> f64>(c: &Color, f: F) -> Color {
+    Color::new((f(c.x()), f(c.y()), f(c.z())))
+}
+
+fn scale_luminance<F: Fn(f64) -> f64>(c: &Color, f: F) -> Color {
+    let l = luminance(c);
+    if l <= 0. {
+        return Color::default();
+    }
+    *c * (f(l) / l)
+}
+
+fn hable_partial(x: f64) -> f64 {
+    const A: f64 = 0.15;
+    const B: f64 = 0.50;
+    const C: f64 = 0.10;
+    const D: f64 = 0.20;
+    const E: f64 = 0.02;
+    const F: f64 = 0.30;
+    (x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F) - E / F
+}
+
+/// the piecewise srgb transfer function, linear near black and a 2.4 power above.
+pub fn srgb_encode(linear: f64) -> f64 {
+    if linear <= 0.0031308 {
+        12.92 * linear.max(0.)
+    } else {
+        1.055 * linear.powf(1. / 2.4) - 0.055
+    }
+}
+
+impl Framebuffer {
+    pub fn tone_mapped(&self, tone_map: &ToneMap) -> Self {
+        self.map(|c| tone_map.apply(c))
+    }
+    /// linear values to srgb encoded ones.
+    pub fn srgb_encoded(&self) -> Self {
+        self.map(|c| per_channel(c, srgb_encode))
+    }
+    /// tone maps, encodes as srgb and quantizes to 8 bits.
+    pub fn to_srgb_image(&self, tone_map: &ToneMap) -> RgbImage {
+        self.tone_mapped(tone_map).srgb_encoded().to_rgb_image()
+    }
+}
@@ -2,7 +2,7 @@ use std::{fmt, ops};
 
 use image::Rgb;
 
-use super::{random_float, random_float_with_range};
+use super::{Pcg32, Sampler, PI};
 #[derive(Default, Debug, Clone, Copy, PartialEq, PartialOrd)]
 pub struct Vec3(f64, f64, f64);
 impl Vec3 {
@@ -53,30 +53,30 @@ impl Vec3 {
         *self / self.len()
     }
     #[inline]
-    pub fn random() -> Self {
-        Self(random_float(), random_float(), random_float())
+    pub fn random(rng: &mut Pcg32) -> Self {
+        Self(rng.random_float(), rng.random_float(), rng.random_float())
     }
-    pub fn random_with_range(min: f64, max: f64) -> Self {
+    pub fn random_with_range(min: f64, max: f64, rng: &mut Pcg32) -> Self {
         Self(
-            random_float_with_range(min, max),
-            random_float_with_range(min, max),
-            random_float_with_range(min, max),
+            rng.random_float_with_range(min, max),
+            rng.random_float_with_range(min, max),
+            rng.random_float_with_range(min, max),
         )
     }
-    pub fn random_in_unit_sphere() -> Self {
-        loop {
-            let p = Self::random_with_range(-1., 1.);
-            if p.len_squared() >= 1. {
-                continue;
-            }
-            return p;
-        }
+    // the sampling helpers map sampler values straight onto the shape instead of
+    // rejecting points, so well spread samples stay well spread.
+    pub fn random_in_unit_sphere(sampler: &mut dyn Sampler) -> Self {
+        Self::random_unit_vector(sampler) * sampler.get_1d().cbrt()
     }
-    pub fn random_unit_vector() -> Self {
-        Self::random_in_unit_sphere().unit_vector()
+    pub fn random_unit_vector(sampler: &mut dyn Sampler) -> Self {
+        let (u, v) = sampler.get_2d();
+        let z = 1. - 2. * u;
+        let r = (1. - z * z).max(0.).sqrt();
+        let phi = 2. * PI * v;
+        Self(r * phi.cos(), r * phi.sin(), z)
     }
-    pub fn random_in_hemisphere(normal: &Vec3) -> Self {
-        let in_unit_sphere = Self::random_in_unit_sphere();
+    pub fn random_in_hemisphere(normal: &Vec3, sampler: &mut dyn Sampler) -> Self {
+        let in_unit_sphere = Self::random_in_unit_sphere(sampler);
         if in_unit_sphere.dot(normal) > 0. {
             return in_unit_sphere;
         }
@@ -96,18 +96,20 @@ impl Vec3 {
         let r_out_parallel = -((1. - r_out_perp.len_squared()).abs().sqrt()) * n;
         r_out_perp + r_out_parallel
     }
-    pub fn random_in_unit_disk() -> Self {
-        loop {
-            let p = Vec3::new((
-                random_float_with_range(-1., 1.),
-                random_float_with_range(-1., 1.),
-                0.,
-            ));
-            if p.len_squared() >= 1. {
-                continue;
-            }
-            return p;
+    /// concentric mapping (Shirley and Chiu 1997), squares of the sample space become
+    /// rings of the disk.
+    pub fn random_in_unit_disk(sampler: &mut dyn Sampler) -> Self {
+        let (u, v) = sampler.get_2d();
+        let (a, b) = (2. * u - 1., 2. * v - 1.);
+        if a == 0. && b == 0. {
+            return Self::default();
         }
+        let (r, theta) = if a.abs() > b.abs() {
+            (a, PI / 4. * (b / a))
+        } else {
+            (b, PI / 2. - PI / 4. * (a / b))
+        };
+        Self(r * theta.cos(), r * theta.sin(), 0.)
     }
     pub fn min(&self, other: &Self) -> Self {
         Self(
@@ -0,0 +1,110 @@
+use ray_tracing_the_next_week::{Point3, Ray, Vec3, AABB, INFINITY};
+
+fn unit_box() -> AABB {
+    AABB::new(Point3::new((0., 0., 0.)), Point3::new((1., 1., 1.)))
+}
+
+fn ray(orig: (f64, f64, f64), dir: (f64, f64, f64)) -> Ray {
+    Ray::new(Point3::new(orig), Vec3::new(dir), 0.)
+}
+
+#[test]
+fn ray_through_the_middle_hits() {
+    let r#box = unit_box();
+    assert!(r#box.hit(&ray((0.5, 0.5, -2.), (0., 0., 1.)), (0.001, INFINITY)));
+    assert!(r#box.hit(&ray((-1., -1., -1.), (1., 1., 1.)), (0.001, INFINITY)));
+    assert!(r#box.hit(&ray((2., 3., 4.), (-1.5, -2.5, -3.5)), (0.001, INFINITY)));
+}
+
+#[test]
+fn ray_beside_the_box_misses() {
+    let r#box = unit_box();
+    assert!(!r#box.hit(&ray((1.5, 0.5, -2.), (0., 0., 1.)), (0.001, INFINITY)));
+    assert!(!r#box.hit(&ray((-1., 0.5, -1.), (1., 0., -1.)), (0.001, INFINITY)));
+}
+
+#[test]
+fn ray_starting_inside_hits() {
+    let r#box = unit_box();
+    assert!(r#box.hit(&ray((0.5, 0.5, 0.5), (0.3, -0.2, 0.7)), (0.001, INFINITY)));
+}
+
+#[test]
+fn interval_is_narrowed_by_t_in() {
+    let r#box = unit_box();
+    let toward = ray((0.5, 0.5, -5.), (0., 0., 1.));
+    // the box spans t in [5, 6]
+    assert!(r#box.hit(&toward, (0.001, INFINITY)));
+    assert!(r#box.hit(&toward, (5.5, 5.6)));
+    assert!(!r#box.hit(&toward, (0.001, 4.)));
+    assert!(!r#box.hit(&toward, (7., INFINITY)));
+    // the same for a ray travelling down the axis
+    let away = ray((0.5, 0.5, 6.), (0., 0., -1.));
+    assert!(!r#box.hit(&away, (0.001, 4.)));
+    assert!(r#box.hit(&away, (0.001, 5.5)));
+}
+
+#[test]
+fn box_behind_the_origin_misses() {
+    let r#box = unit_box();
+    assert!(!r#box.hit(&ray((0.5, 0.5, 3.), (0., 0., 1.)), (0.001, INFINITY)));
+    assert!(!r#box.hit(&ray((3., 3., 3.), (1., 1., 1.)), (0.001, INFINITY)));
+}
+
+#[test]
+fn axis_parallel_rays() {
+    let r#box = unit_box();
+    for axis in 0..3 {
+        let mut inside = [0.5; 3];
+        inside[axis] = -1.;
+        let mut dir = [0.; 3];
+        dir[axis] = 1.;
+        let to_tuple = |v: [f64; 3]| (v[0], v[1], v[2]);
+        assert!(r#box.hit(&ray(to_tuple(inside), to_tuple(dir)), (0.001, INFINITY)));
+
+        // parallel to the box but outside one of the other slabs
+        let mut outside = inside;
+        outside[(axis + 1) % 3] = 1.5;
+        assert!(!r#box.hit(&ray(to_tuple(outside), to_tuple(dir)), (0.001, INFINITY)));
+        outside[(axis + 1) % 3] = -0.5;
+        assert!(!r#box.hit(&ray(to_tuple(outside), to_tuple(dir)), (0.001, INFINITY)));
+    }
+}
+
+#[test]
+fn grazing_a_face_or_an_edge_hits() {
+    let r#box = unit_box();
+    // sliding along the top face
+    assert!(r#box.hit(&ray((-1., 1., 0.5), (1., 0., 0.)), (0.001, INFINITY)));
+    // along an edge
+    assert!(r#box.hit(&ray((-1., 1., 1.), (1., 0., 0.)), (0.001, INFINITY)));
+    // diagonally, touching the box only where the x and y faces meet
+    assert!(r#box.hit(&ray((2., 0., 0.5), (-1., 1., 0.)), (0.001, INFINITY)));
+    // just past the face
+    assert!(!r#box.hit(&ray((-1., 1. + 1e-9, 0.5), (1., 0., 0.)), (0.001, INFINITY)));
+}
+
+#[test]
+fn origin_on_a_slab_plane_with_zero_direction_is_not_nan() {
+    // (min - orig) / 0 is 0 / 0 on these axes
+    let r#box = unit_box();
+    assert!(r#box.hit(&ray((0., 0.5, -1.), (0., 0., 1.)), (0.001, INFINITY)));
+    assert!(r#box.hit(&ray((1., 1., -1.), (0., 0., 1.)), (0.001, INFINITY)));
+    assert!(r#box.hit(&ray((0., 0.5, -1.), (-0., 0., 1.)), (0.001, INFINITY)));
+    assert!(r#box.hit(&ray((0.5, 0., 2.), (0., -0., -1.)), (0.001, INFINITY)));
+    // still misses when another axis rules it out
+    assert!(!r#box.hit(&ray((0., 0.5, -1.), (0., 0., 1.)), (0.001, 0.5)));
+    assert!(!r#box.hit(&ray((0., 1.5, -1.), (-0., 0., 1.)), (0.001, INFINITY)));
+}
+
+#[test]
+fn ray_keeps_its_inverse_direction_in_sync() {
+    let mut r = ray((0., 0., 0.), (2., -4., 0.));
+    assert_eq!(r.inv_direction().x(), 0.5);
+    assert_eq!(r.inv_direction().y(), -0.25);
+    assert!(r.inv_direction().z().is_infinite());
+    assert_eq!(r.sign(), &[0, 1, 0]);
+    r.set_direction(Vec3::new((-1., 1., -0.)));
+    assert_eq!(r.sign(), &[1, 0, 1]);
+    assert!(unit_box().hit(&r, (0., INFINITY)));
+}
@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use ray_tracing_the_next_week::{
+    luminance, AdaptiveSampling, Camera, Color, DiffuseLight, HittableList, Lambertian,
+    PixelEstimate, Point3, RenderSettings, Renderer, Vec3, XZPlane,
+};
+
+const MIN_SAMPLES: usize = 32;
+const MAX_SAMPLES: usize = 128;
+const SIZE: u32 = 16;
+
+// a floor lit only by a big light just out of the picture fills the bottom half, black
+// sky the top. the light covers enough of the floor's view that no pixel there misses it
+// with all of its first samples, which would look perfectly converged
+fn floor_under_sky() -> Renderer {
+    let white = Arc::new(Lambertian::new(Color::new((0.73, 0.73, 0.73))));
+    let light = Arc::new(DiffuseLight::with_solid_color(Color::new((4., 4., 4.))));
+    let mut world = HittableList::new();
+    world.add(Arc::new(XZPlane::new(
+        white,
+        (-100., 100.),
+        (-100., 100.),
+        0.,
+    )));
+    world.add(Arc::new(XZPlane::new(light, (-5., 5.), (-6., 2.), 5.)));
+    let camera = Camera::new(
+        Point3::new((0., 1., -6.)),
+        Point3::new((0., 1., 0.)),
+        Vec3::new((0., 1., 0.)),
+        40.,
+        1.,
+        0.,
+        6.,
+        0.,
+        1.,
+    );
+    let settings = RenderSettings {
+        image_width: SIZE,
+        image_height: SIZE,
+        samples_per_pixel: MAX_SAMPLES,
+        adaptive: Some(AdaptiveSampling {
+            min_samples: MIN_SAMPLES,
+            threshold: 0.01,
+        }),
+        max_depth: 4,
+        ..Default::default()
+    };
+    Renderer::new(camera, Arc::new(world), Color::default(), settings)
+}
+
+#[test]
+fn flat_sky_stops_early_and_noisy_floor_takes_every_sample() {
+    let (_, sample_map) = floor_under_sky().render_with_sample_map();
+    for x in 0..SIZE {
+        // the top rows only ever see the background
+        for y in 0..3 {
+            assert_eq!(sample_map.get(x, y), MIN_SAMPLES, "pixel {} {}", x, y);
+        }
+        for y in SIZE - 3..SIZE {
+            assert_eq!(sample_map.get(x, y), MAX_SAMPLES, "pixel {} {}", x, y);
+        }
+    }
+}
+
+#[test]
+fn welford_variance_matches_the_two_pass_formula() {
+    let samples: Vec<Color> = (0..50)
+        .map(|i| {
+            let t = (i as f64 * 0.37).sin();
+            Color::new((1. + t, 0.5 * t * t, 2. - t))
+        })
+        .collect();
+    let mut estimate = PixelEstimate::default();
+    for &sample in &samples {
+        estimate.add(sample);
+    }
+    let luminances: Vec<f64> = samples.iter().map(luminance).collect();
+    let mean = luminances.iter().sum::<f64>() / luminances.len() as f64;
+    let variance =
+        luminances.iter().map(|l| (l - mean).powi(2)).sum::<f64>() / (luminances.len() - 1) as f64;
+    assert_eq!(estimate.count(), 50);
+    assert!((estimate.variance() - variance).abs() < 1e-12);
+    let error = (variance / 50.).sqrt() / mean;
+    assert!((estimate.relative_error() - error).abs() < 1e-12);
+}
+
+#[test]
+fn convergence_needs_the_minimum_samples_and_a_small_error() {
+    let adaptive = AdaptiveSampling {
+        min_samples: 4,
+        threshold: 0.01,
+    };
+    let mut flat = PixelEstimate::default();
+    for _ in 0..3 {
+        flat.add(Color::new((0.5, 0.5, 0.5)));
+    }
+    assert!(!adaptive.converged(&flat));
+    flat.add(Color::new((0.5, 0.5, 0.5)));
+    assert!(adaptive.converged(&flat));
+
+    let mut noisy = PixelEstimate::default();
+    for i in 0..100 {
+        let value = if i % 2 == 0 { 0. } else { 1. };
+        noisy.add(Color::new((value, value, value)));
+    }
+    assert!(!adaptive.converged(&noisy));
+}
@@ -0,0 +1,119 @@
+use std::sync::Arc;
+
+use ray_tracing_the_next_week::{
+    AnimatedTransform, Box, Color, Hittable, Keyframe, Lambertian, Pcg32, Point3, Quaternion, Ray,
+    Vec3, AABB, INFINITY,
+};
+
+// a box off its own origin, tumbling, stretching and flying across the shutter
+fn tumbling_box() -> AnimatedTransform {
+    let white = Arc::new(Lambertian::new(Color::new((0.73, 0.73, 0.73))));
+    let r#box = Arc::new(Box::new(
+        Point3::new((0.5, -1., -0.25)),
+        Point3::new((2., 1., 0.25)),
+        white,
+    ));
+    let axis = Vec3::new((1., 2., 0.5));
+    AnimatedTransform::new(
+        r#box,
+        vec![
+            Keyframe::new(
+                0.,
+                Vec3::new((-2., 0., 0.)),
+                Quaternion::identity(),
+                Vec3::new((1., 1., 1.)),
+            ),
+            Keyframe::new(
+                0.4,
+                Vec3::new((0., 1., 1.)),
+                Quaternion::from_axis_angle(axis, 160.),
+                Vec3::new((2., 0.5, 1.)),
+            ),
+            Keyframe::new(
+                1.,
+                Vec3::new((2., -1., 0.)),
+                Quaternion::from_axis_angle(Vec3::new((0., 0., 1.)), -110.),
+                Vec3::new((0.5, 1.5, 3.)),
+            ),
+        ],
+    )
+}
+
+// where the ray enters and leaves the box, if it does
+fn slabs(r#box: &AABB, ray: &Ray) -> Option<(f64, f64)> {
+    let (mut enter, mut leave) = (-INFINITY, INFINITY);
+    for axis in 0..3 {
+        let inverse = 1. / ray.direction()[axis];
+        let t0 = (r#box.min()[axis] - ray.orig()[axis]) * inverse;
+        let t1 = (r#box.max()[axis] - ray.orig()[axis]) * inverse;
+        enter = enter.max(t0.min(t1));
+        leave = leave.min(t0.max(t1));
+    }
+    Some((enter, leave)).filter(|(enter, leave)| enter <= leave)
+}
+
+#[test]
+fn hits_over_the_shutter_stay_in_the_bounding_box() {
+    let object = tumbling_box();
+    let mut r#box = AABB::default();
+    assert!(object.bounding_box((0., 1.), &mut r#box));
+    let mut rng = Pcg32::new(3, 0);
+    let mut hits = 0;
+    for _ in 0..20_000 {
+        let origin = Point3::new((
+            rng.random_float_with_range(-20., 20.),
+            rng.random_float_with_range(-20., 20.),
+            rng.random_float_with_range(-20., 20.),
+        ));
+        // aimed somewhere inside the box, so many hit and the rest pass close by
+        let target = Point3::new((
+            rng.random_float_with_range(r#box.min().x(), r#box.max().x()),
+            rng.random_float_with_range(r#box.min().y(), r#box.max().y()),
+            rng.random_float_with_range(r#box.min().z(), r#box.max().z()),
+        ));
+        let ray = Ray::new(origin, target - origin, rng.random_float());
+        let hit = object.hit(&ray, 0.001, INFINITY).is_some();
+        hits += hit as usize;
+        match slabs(&r#box, &ray) {
+            Some((enter, leave)) => {
+                let eps = 1e-6;
+                assert!(object.hit(&ray, 0.001, enter - eps).is_none());
+                assert!(object.hit(&ray, leave + eps, INFINITY).is_none());
+            }
+            None => assert!(!hit, "{:?} hits outside the box", ray),
+        }
+    }
+    // enough of them hit for the check to mean something
+    assert!(hits > 1_000, "only {} hits", hits);
+}
+
+#[test]
+fn keyframe_scale_must_be_invertible() {
+    let keyframe =
+        |scale: Vec3| Keyframe::try_new(0., Vec3::default(), Quaternion::identity(), scale);
+    assert!(keyframe(Vec3::new((1., 0., 1.))).is_none());
+    assert!(keyframe(Vec3::new((f64::NAN, 1., 1.))).is_none());
+    assert!(keyframe(Vec3::new((1., 1., INFINITY))).is_none());
+    // mirroring is fine
+    assert!(keyframe(Vec3::new((-1., 2., 0.5))).is_some());
+}
+
+#[test]
+#[should_panic(expected = "scale changes sign")]
+fn scale_changing_sign_between_keyframes_is_refused() {
+    let white = Arc::new(Lambertian::new(Color::new((0.73, 0.73, 0.73))));
+    let r#box = Arc::new(Box::new(
+        Point3::new((-1., -1., -1.)),
+        Point3::new((1., 1., 1.)),
+        white,
+    ));
+    let keyframe = |time: f64, x: f64| {
+        Keyframe::new(
+            time,
+            Vec3::default(),
+            Quaternion::identity(),
+            Vec3::new((x, 1., 1.)),
+        )
+    };
+    AnimatedTransform::new(r#box, vec![keyframe(0., 1.), keyframe(1., -1.)]);
+}
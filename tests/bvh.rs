@@ -0,0 +1,72 @@
+use std::sync::Arc;
+
+use ray_tracing_the_next_week::{
+    BVHNode, Color, Hittable, Lambertian, MovingSphere, Pcg32, Point3, Sphere, SplitMethod,
+};
+
+// well over the size where the build starts handing subtrees to other threads
+const COUNT: usize = 20_000;
+
+fn spheres() -> Vec<Arc<dyn Hittable>> {
+    let white = Arc::new(Lambertian::new(Color::new((0.73, 0.73, 0.73))));
+    let mut rng = Pcg32::new(7, 0);
+    (0..COUNT)
+        .map(|_| {
+            let center = Point3::new((
+                rng.random_float_with_range(-100., 100.),
+                rng.random_float_with_range(-100., 100.),
+                rng.random_float_with_range(-100., 100.),
+            ));
+            let radius = rng.random_float_with_range(0.1, 2.);
+            Arc::new(Sphere::new(center, radius, white.clone())) as Arc<dyn Hittable>
+        })
+        .collect()
+}
+
+#[test]
+fn thread_count_does_not_change_the_tree() {
+    let objects = spheres();
+    for split_method in [SplitMethod::SurfaceAreaHeuristic, SplitMethod::Median] {
+        let single = BVHNode::with_split_method(objects.clone(), (0., 1.), split_method, 1);
+        for threads in [2, 3, 8] {
+            let parallel =
+                BVHNode::with_split_method(objects.clone(), (0., 1.), split_method, threads);
+            // the debug output lists every node, so this compares the node arrays
+            assert_eq!(
+                format!("{:?}", single),
+                format!("{:?}", parallel),
+                "{:?} with {} threads",
+                split_method,
+                threads
+            );
+        }
+    }
+}
+
+#[test]
+fn rebuild_undoes_what_refitting_costs() {
+    let white = Arc::new(Lambertian::new(Color::new((0.73, 0.73, 0.73))));
+    let mut rng = Pcg32::new(3, 0);
+    let mut point = || {
+        Point3::new((
+            rng.random_float_with_range(-100., 100.),
+            rng.random_float_with_range(-100., 100.),
+            rng.random_float_with_range(-100., 100.),
+        ))
+    };
+    // every sphere jumps somewhere else at time 1
+    let objects: Vec<Arc<dyn Hittable>> = (0..2000)
+        .map(|_| {
+            let center = (point(), point());
+            Arc::new(MovingSphere::new(center, (0., 2.), 1., white.clone())) as Arc<dyn Hittable>
+        })
+        .collect();
+    let mut bvh = BVHNode::from_objects(objects, (0., 0.));
+    assert_eq!(bvh.cost_ratio(), 1.);
+    bvh.refit((1., 1.));
+    let refit_cost = bvh.sah_cost();
+    assert!(bvh.cost_ratio() > 1.5, "cost ratio {}", bvh.cost_ratio());
+    bvh.rebuild((1., 1.));
+    assert_eq!(bvh.cost_ratio(), 1.);
+    assert!(bvh.sah_cost() < refit_cost);
+}
@@ -0,0 +1,113 @@
+use std::sync::Arc;
+
+use ray_tracing_the_next_week::{
+    Box, Camera, Color, Denoiser, DiffuseLight, Framebuffer, HittableList, Lambertian, Mat4,
+    Point3, RenderSettings, Renderer, Transform, Vec3, XYPlane, XZPlane, YZPlane,
+};
+
+const SIZE: u32 = 40;
+
+fn cornell_box(samples_per_pixel: usize, seed: u64) -> Renderer {
+    let red = Arc::new(Lambertian::new(Color::new((0.65, 0.05, 0.05))));
+    let white = Arc::new(Lambertian::new(Color::new((0.73, 0.73, 0.73))));
+    let green = Arc::new(Lambertian::new(Color::new((0.12, 0.45, 0.15))));
+    let light = Arc::new(DiffuseLight::with_solid_color(Color::new((15., 15., 15.))));
+    let light = Arc::new(XZPlane::new(light, (213., 343.), (227., 332.), 554.));
+    let mut world = HittableList::new();
+    world.add(Arc::new(YZPlane::new(green, (0., 555.), (0., 555.), 555.)));
+    world.add(Arc::new(YZPlane::new(red, (0., 555.), (0., 555.), 0.)));
+    world.add(light.clone());
+    world.add(Arc::new(XZPlane::new(
+        white.clone(),
+        (0., 555.),
+        (0., 555.),
+        0.,
+    )));
+    world.add(Arc::new(XZPlane::new(
+        white.clone(),
+        (0., 555.),
+        (0., 555.),
+        555.,
+    )));
+    world.add(Arc::new(XYPlane::new(
+        white.clone(),
+        (0., 555.),
+        (0., 555.),
+        555.,
+    )));
+    world.add(Arc::new(Transform::new(
+        Arc::new(Box::new(
+            Point3::new((0., 0., 0.)),
+            Point3::new((165., 330., 165.)),
+            white,
+        )),
+        Mat4::translation(Vec3::new((265., 0., 295.)))
+            * Mat4::rotation(Vec3::new((0., 1., 0.)), 15.),
+    )));
+    let camera = Camera::new(
+        Point3::new((278., 278., -800.)),
+        Point3::new((278., 278., 0.)),
+        Vec3::new((0., 1., 0.)),
+        40.,
+        1.,
+        0.,
+        10.,
+        0.,
+        1.,
+    );
+    let settings = RenderSettings {
+        image_width: SIZE,
+        image_height: SIZE,
+        samples_per_pixel,
+        max_depth: 8,
+        seed,
+        ..Default::default()
+    };
+    Renderer::new(camera, Arc::new(world), Color::default(), settings).with_lights(light)
+}
+
+// relative, so the few pixels half covering the light don't drown out the rest
+fn relative_mean_squared_error(image: &Framebuffer, reference: &Framebuffer) -> f64 {
+    let total: f64 = image
+        .pixels()
+        .iter()
+        .zip(reference.pixels())
+        .map(|(a, b)| (*a - *b).len_squared() / (b.len_squared() + 0.01))
+        .sum();
+    total / image.pixels().len() as f64
+}
+
+#[test]
+fn denoising_brings_a_noisy_render_closer_to_the_reference() {
+    let reference = cornell_box(512, 1).render();
+    let renderer = cornell_box(8, 2);
+    let mut accumulator = renderer.accumulator();
+    renderer.render_pass(&mut accumulator, 8);
+    let noisy = accumulator.to_framebuffer();
+    let aovs = renderer.render_aovs(4);
+    let denoised = Denoiser::default().denoise(&noisy, &accumulator.variances(), &aovs);
+
+    let before = relative_mean_squared_error(&noisy, &reference);
+    let after = relative_mean_squared_error(&denoised, &reference);
+    assert!(
+        after < before / 2.,
+        "error went from {} to only {}",
+        before,
+        after
+    );
+}
+
+#[test]
+fn noiseless_image_is_left_alone() {
+    let renderer = cornell_box(1, 0);
+    let aovs = renderer.render_aovs(1);
+    let mut image = Framebuffer::new(SIZE, SIZE);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            image.put_pixel(x, y, Color::new((x as f64 / 8., y as f64 / 8., 1.)));
+        }
+    }
+    let variances = vec![0.; (SIZE * SIZE) as usize];
+    let denoised = Denoiser::default().denoise(&image, &variances, &aovs);
+    assert!(relative_mean_squared_error(&denoised, &image) < 1e-12);
+}
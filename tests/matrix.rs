@@ -0,0 +1,141 @@
+use ray_tracing_the_next_week::{Mat4, Point3, Quaternion, Vec3};
+
+fn assert_close(a: Vec3, b: Vec3) {
+    assert!((a - b).len() < 1e-9, "{:?} is not {:?}", a, b);
+}
+
+fn assert_identity(m: &Mat4) {
+    for v in [
+        Vec3::new((1., 0., 0.)),
+        Vec3::new((0., 1., 0.)),
+        Vec3::new((0., 0., 1.)),
+    ] {
+        assert_close(m.transform_vector(&v), v);
+    }
+    assert_close(m.transform_point(&Point3::default()), Point3::default());
+}
+
+#[test]
+fn inverse_undoes_the_transform() {
+    let m = Mat4::from_trs(
+        Vec3::new((3., -1., 2.)),
+        Quaternion::from_axis_angle(Vec3::new((1., 1., 0.)), 35.),
+        Vec3::new((2., 0.5, -3.)),
+    );
+    let inverse = m.inverse().unwrap();
+    assert_identity(&(m * inverse));
+    assert_identity(&(inverse * m));
+    // a zero on the diagonal needs the rows swapped
+    let swap = Mat4::new([
+        [0., 1., 0., 1.],
+        [1., 0., 0., 2.],
+        [0., 0., 2., 3.],
+        [0., 0., 0., 1.],
+    ]);
+    assert_identity(&(swap * swap.inverse().unwrap()));
+}
+
+#[test]
+fn singular_matrix_has_no_inverse() {
+    assert!(Mat4::scaling(Vec3::new((1., 0., 1.))).inverse().is_none());
+    let flat = Mat4::new([
+        [1., 2., 3., 0.],
+        [2., 4., 6., 0.],
+        [0., 0., 1., 0.],
+        [0., 0., 0., 1.],
+    ]);
+    assert!(flat.inverse().is_none());
+}
+
+#[test]
+fn quaternion_turns_the_same_way_as_the_matrix() {
+    let axis = Vec3::new((1., 2., -0.5));
+    let v = Vec3::new((0.3, -1., 2.));
+    for angle in [0., 30., 90., 180., 270.] {
+        let q = Quaternion::from_axis_angle(axis, angle);
+        let expected = Mat4::rotation(axis, angle).transform_vector(&v);
+        assert_close(q.rotate(&v), expected);
+        assert_close(q.to_mat4().transform_vector(&v), expected);
+    }
+    let quarter = Quaternion::from_axis_angle(Vec3::new((0., 0., 1.)), 90.);
+    assert_close(
+        quarter.rotate(&Vec3::new((1., 0., 0.))),
+        Vec3::new((0., 1., 0.)),
+    );
+    // the conjugate turns back
+    assert_close(quarter.conjugate().rotate(&quarter.rotate(&v)), v);
+}
+
+#[test]
+fn normalize_keeps_the_rotation() {
+    // twice the quaternion of a quarter turn around y
+    let long = Quaternion::new(2f64.sqrt(), Vec3::new((0., 2f64.sqrt(), 0.)));
+    let quarter = Quaternion::from_axis_angle(Vec3::new((0., 1., 0.)), 90.);
+    let normalized = long.normalize();
+    assert!((normalized.dot(&normalized) - 1.).abs() < 1e-12);
+    let v = Vec3::new((1., 2., 3.));
+    assert_close(normalized.rotate(&v), quarter.rotate(&v));
+    assert_close(long.to_mat4().transform_vector(&v), quarter.rotate(&v));
+}
+
+#[test]
+fn slerp_moves_at_constant_speed() {
+    let z = Vec3::new((0., 0., 1.));
+    let x = Vec3::new((1., 0., 0.));
+    let (a, b) = (Quaternion::identity(), Quaternion::from_axis_angle(z, 120.));
+    assert_close(a.slerp(&b, 0.).rotate(&x), x);
+    assert_close(a.slerp(&b, 1.).rotate(&x), b.rotate(&x));
+    for t in [0.25f64, 0.5, 0.8] {
+        let angle = (120. * t).to_radians();
+        assert_close(
+            a.slerp(&b, t).rotate(&x),
+            Vec3::new((angle.cos(), angle.sin(), 0.)),
+        );
+    }
+    // nearly the same rotation takes the linear path without dividing by sin θ ≈ 0
+    let c = Quaternion::from_axis_angle(z, 1e-6);
+    let angle = 0.5e-6f64.to_radians();
+    assert_close(
+        a.slerp(&c, 0.5).rotate(&x),
+        Vec3::new((angle.cos(), angle.sin(), 0.)),
+    );
+}
+
+#[test]
+fn slerp_takes_the_short_way_round() {
+    let z = Vec3::new((0., 0., 1.));
+    let x = Vec3::new((1., 0., 0.));
+    // 300 degrees one way is 60 the other, and its quaternion has a negative dot with
+    // the identity
+    let (a, b) = (Quaternion::identity(), Quaternion::from_axis_angle(z, 300.));
+    assert!(a.dot(&b) < 0.);
+    let angle = (-30f64).to_radians();
+    assert_close(
+        a.slerp(&b, 0.5).rotate(&x),
+        Vec3::new((angle.cos(), angle.sin(), 0.)),
+    );
+    assert!((a.angle_to(&b) - 60f64.to_radians()).abs() < 1e-9);
+}
+
+#[test]
+fn from_trs_scales_then_rotates_then_translates() {
+    let m = Mat4::from_trs(
+        Vec3::new((0., 0., 5.)),
+        Quaternion::from_axis_angle(Vec3::new((0., 0., 1.)), 90.),
+        Vec3::new((2., 1., 1.)),
+    );
+    assert_close(
+        m.transform_point(&Point3::new((1., 0., 0.))),
+        Point3::new((0., 2., 5.)),
+    );
+    assert_close(
+        m.transform_point(&Point3::new((0., 1., 0.))),
+        Point3::new((-1., 0., 5.)),
+    );
+    // vectors aren't moved
+    assert_close(
+        m.transform_vector(&Vec3::new((1., 0., 0.))),
+        Vec3::new((0., 2., 0.)),
+    );
+    assert!((m.determinant() - 2.).abs() < 1e-9);
+}
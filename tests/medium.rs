@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+use ray_tracing_the_next_week::{
+    Box, Color, ConstantMedium, Hittable, HittableList, Lambertian, Mat4, Point3, Ray, Transform,
+    Vec3, INFINITY,
+};
+
+const SAMPLES: usize = 20_000;
+
+// unit density fog filling the box from `low` to `high`
+fn fog(low: (f64, f64, f64), high: (f64, f64, f64)) -> Arc<dyn Hittable> {
+    let white = Arc::new(Lambertian::new(Color::new((0.73, 0.73, 0.73))));
+    let boundary = Arc::new(Box::new(Point3::new(low), Point3::new(high), white));
+    Arc::new(ConstantMedium::from_color(
+        boundary,
+        Color::new((1., 1., 1.)),
+        1.,
+    ))
+}
+
+// the fraction of rays along x through the middle of the unit square that get through
+fn transmittance(world: &dyn Hittable) -> f64 {
+    let through = (0..SAMPLES)
+        .filter(|&i| {
+            let u = (i as f64 + 0.5) / SAMPLES as f64;
+            let ray = Ray::new(Point3::new((-1., 0.5, 0.5)), Vec3::new((1., 0., 0.)), 0.)
+                .with_medium_sample(u);
+            world.hit(&ray, 0.001, INFINITY).is_none()
+        })
+        .count();
+    through as f64 / SAMPLES as f64
+}
+
+fn assert_transmittance(world: &dyn Hittable, optical_depth: f64) {
+    let measured = transmittance(world);
+    let expected = (-optical_depth).exp();
+    assert!(
+        (measured - expected).abs() < 0.1 * expected,
+        "transmittance {} instead of {}",
+        measured,
+        expected
+    );
+}
+
+#[test]
+fn one_medium_follows_beer_lambert() {
+    assert_transmittance(fog((0., 0., 0.), (2., 1., 1.)).as_ref(), 2.);
+}
+
+#[test]
+fn overlapping_media_add_up() {
+    let mut world = HittableList::new();
+    world.add(fog((0., 0., 0.), (2., 1., 1.)));
+    world.add(fog((0., 0., 0.), (2., 1., 1.)));
+    assert_transmittance(&world, 4.);
+}
+
+#[test]
+fn consecutive_media_add_up() {
+    let mut world = HittableList::new();
+    world.add(fog((0., 0., 0.), (1., 1., 1.)));
+    world.add(fog((1., 0., 0.), (2., 1., 1.)));
+    assert_transmittance(&world, 2.);
+}
+
+#[test]
+fn media_under_a_transform_draw_their_own_free_paths() {
+    let mut world = HittableList::new();
+    world.add(Arc::new(Transform::new(
+        fog((0., 0., 0.), (2., 1., 1.)),
+        Mat4::translation(Vec3::new((0.5, 0., 0.))),
+    )));
+    world.add(fog((0., 0., 0.), (2., 1., 1.)));
+    // 1.5 inside both, 0.5 inside each one alone
+    assert_transmittance(&world, 4.);
+}
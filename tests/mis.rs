@@ -0,0 +1,99 @@
+use std::sync::Arc;
+
+use ray_tracing_the_next_week::{
+    luminance, Camera, Color, DiffuseLight, Hittable, HittableList, Lambertian, Mat4, Metal,
+    Point3, RenderSettings, Renderer, Sphere, Transform, Vec3, XZPlane,
+};
+
+const SIZE: u32 = 24;
+
+fn settings() -> RenderSettings {
+    RenderSettings {
+        image_width: SIZE,
+        image_height: SIZE,
+        samples_per_pixel: 256,
+        max_depth: 4,
+        ..Default::default()
+    }
+}
+
+fn camera() -> Camera {
+    Camera::new(
+        Point3::new((0., 2., -8.)),
+        Point3::new((0., 0., 0.)),
+        Vec3::new((0., 1., 0.)),
+        40.,
+        1.,
+        0.,
+        8.,
+        0.,
+        1.,
+    )
+}
+
+fn with_or_without(renderer: Renderer, light: Arc<dyn Hittable>, sampled: bool) -> Renderer {
+    match sampled {
+        true => renderer.with_lights(light),
+        false => renderer,
+    }
+}
+
+// a rough metal floor under a square light, seen at an angle so plenty of the lobe
+// ends up below the floor
+fn metal_floor(fuzz: f64, light_sampled: bool) -> Renderer {
+    let metal = Arc::new(Metal::new(Color::new((0.8, 0.8, 0.8)), fuzz));
+    let light = Arc::new(DiffuseLight::with_solid_color(Color::new((4., 4., 4.))));
+    let light = Arc::new(XZPlane::new(light, (-2., 2.), (-2., 2.), 4.));
+    let mut world = HittableList::new();
+    world.add(Arc::new(XZPlane::new(metal, (-50., 50.), (-50., 50.), 0.)));
+    world.add(light.clone());
+    let renderer = Renderer::new(camera(), Arc::new(world), Color::default(), settings());
+    with_or_without(renderer, light, light_sampled)
+}
+
+// a sphere light squashed unevenly over a white floor
+fn stretched_light(light_sampled: bool) -> Renderer {
+    let white = Arc::new(Lambertian::new(Color::new((0.73, 0.73, 0.73))));
+    let light = Arc::new(DiffuseLight::with_solid_color(Color::new((4., 4., 4.))));
+    let light: Arc<dyn Hittable> = Arc::new(Transform::new(
+        Arc::new(Sphere::new(Point3::default(), 1., light)),
+        Mat4::translation(Vec3::new((0., 3., 0.))) * Mat4::scaling(Vec3::new((3., 0.5, 1.5))),
+    ));
+    let mut world = HittableList::new();
+    world.add(Arc::new(XZPlane::new(white, (-50., 50.), (-50., 50.), 0.)));
+    world.add(light.clone());
+    let renderer = Renderer::new(camera(), Arc::new(world), Color::default(), settings());
+    with_or_without(renderer, light, light_sampled)
+}
+
+fn mean_luminance(renderer: &Renderer) -> f64 {
+    let image = renderer.render();
+    image.pixels().iter().map(luminance).sum::<f64>() / image.pixels().len() as f64
+}
+
+#[test]
+fn light_sampling_a_rough_metal_matches_plain_path_tracing() {
+    for fuzz in [0.5, 0.8, 0.95, 1.] {
+        let plain = mean_luminance(&metal_floor(fuzz, false));
+        let sampled = mean_luminance(&metal_floor(fuzz, true));
+        assert!(
+            (plain - sampled).abs() < 0.02 * plain,
+            "fuzz {}: {} without light sampling, {} with",
+            fuzz,
+            plain,
+            sampled
+        );
+    }
+}
+
+#[test]
+fn light_sampling_an_unevenly_scaled_light_matches_plain_path_tracing() {
+    let plain = mean_luminance(&stretched_light(false));
+    let sampled = mean_luminance(&stretched_light(true));
+    assert!(
+        (plain - sampled).abs() < 0.02 * plain,
+        "{} without light sampling, {} with",
+        plain,
+        sampled
+    );
+}
@@ -0,0 +1,108 @@
+use std::path::PathBuf;
+
+use ray_tracing_the_next_week::{
+    load_obj, Color, Hittable, LoadError, Point3, Ray, TriangleMesh, Vec3, INFINITY,
+};
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+// the albedo of the mesh's material where a ray straight down onto (x, y) hits it
+fn albedo_at_or_none(mesh: &TriangleMesh, x: f64, y: f64) -> Option<Color> {
+    let down = Ray::new(Point3::new((x, y, 1.)), Vec3::new((0., 0., -1.)), 0.);
+    mesh.hit(&down, 0.001, INFINITY)
+        .map(|rec| mesh.material().albedo(&rec))
+}
+
+fn albedo_at(mesh: &TriangleMesh, x: f64, y: f64) -> Color {
+    albedo_at_or_none(mesh, x, y).expect("ray misses the mesh")
+}
+
+fn assert_close(a: Color, b: Color) {
+    assert!((a - b).len() < 1e-9, "{:?} is not {:?}", a, b);
+}
+
+#[test]
+fn each_material_gets_its_own_mesh() {
+    let meshes = load_obj(fixture("scene.obj")).unwrap();
+    let triangles: Vec<usize> = meshes.iter().map(|mesh| mesh.triangle_count()).collect();
+    assert_eq!(triangles, [2, 2, 1]);
+    assert_eq!(meshes[0].vertex_count(), 4);
+}
+
+#[test]
+fn faces_use_the_material_named_by_usemtl() {
+    let meshes = load_obj(fixture("scene.obj")).unwrap();
+    assert_close(
+        albedo_at(&meshes[0], 0.25, 0.5),
+        Color::new((0.8, 0.1, 0.1)),
+    );
+    // lights show their emission clamped to 1
+    assert_close(albedo_at(&meshes[2], 4.25, 0.25), Color::new((1., 1., 1.)));
+}
+
+#[test]
+fn negative_indices_count_back_from_the_last_vertex() {
+    let meshes = load_obj(fixture("scene.obj")).unwrap();
+    let textured = &meshes[1];
+    assert_eq!(textured.vertex_count(), 4);
+    // the texture is green on the left half and blue on the right, so the uvs came along
+    assert_close(albedo_at(textured, 2.25, 0.5), Color::new((0., 1., 0.)));
+    assert_close(albedo_at(textured, 2.75, 0.5), Color::new((0., 0., 1.)));
+    let outside = Ray::new(Point3::new((1.5, 0.5, 1.)), Vec3::new((0., 0., -1.)), 0.);
+    assert!(textured.hit(&outside, 0.001, INFINITY).is_none());
+}
+
+#[test]
+fn face_out_of_range_reports_its_line() {
+    match load_obj(fixture("bad_face.obj")) {
+        Err(LoadError::Parse { line, .. }) => assert_eq!(line, 5),
+        other => panic!("expected a parse error, got {:?}", other.err()),
+    }
+}
+
+#[test]
+fn missing_material_library_is_an_io_error() {
+    match load_obj(fixture("missing_mtl.obj")) {
+        Err(LoadError::Io { path, .. }) => assert_eq!(path, fixture("does_not_exist.mtl")),
+        other => panic!("expected an io error, got {:?}", other.err()),
+    }
+}
+
+#[test]
+fn usemtl_switching_back_adds_to_the_earlier_mesh() {
+    let meshes = load_obj(fixture("switching.obj")).unwrap();
+    assert_eq!(meshes.len(), 2);
+    let (red, green) = (&meshes[0], &meshes[1]);
+    assert_eq!((red.triangle_count(), green.triangle_count()), (4, 4));
+    // the edges shared between the materials are in both meshes
+    assert_eq!((red.vertex_count(), green.vertex_count()), (8, 8));
+    for x in [0.5, 2.5] {
+        assert_close(albedo_at(red, x, 0.5), Color::new((0.8, 0.1, 0.1)));
+        assert!(albedo_at_or_none(green, x, 0.5).is_none());
+    }
+    for x in [1.5, 3.5] {
+        assert_close(albedo_at(green, x, 0.5), Color::new((0.1, 0.8, 0.1)));
+        assert!(albedo_at_or_none(red, x, 0.5).is_none());
+    }
+}
+
+#[test]
+fn missing_texture_reports_the_texture_and_its_line() {
+    match load_obj(fixture("missing_texture.obj")) {
+        Err(LoadError::Texture {
+            path,
+            line,
+            texture,
+            ..
+        }) => {
+            assert_eq!(path, fixture("missing_texture.mtl"));
+            assert_eq!(line, 3);
+            assert_eq!(texture, fixture("does_not_exist.png"));
+        }
+        other => panic!("expected a texture error, got {:?}", other.err()),
+    }
+}
@@ -0,0 +1,293 @@
+use std::{convert::TryInto, fs, path::PathBuf};
+
+use ray_tracing_the_next_week::{f32_to_half, Color, ExrImage, ExrPixelType, Framebuffer};
+
+// a file in the temp directory, unique to the test writing it
+fn scratch(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("output-test-{}-{}", std::process::id(), name))
+}
+
+fn write_and_read<F: FnOnce(&PathBuf)>(name: &str, write: F) -> Vec<u8> {
+    let path = scratch(name);
+    write(&path);
+    let bytes = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    bytes
+}
+
+fn f32_at(bytes: &[u8], at: usize) -> f32 {
+    f32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+fn i32_at(bytes: &[u8], at: usize) -> i32 {
+    i32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+// every pixel a different color, so rows and columns can't be mixed up
+fn gradient(width: u32, height: u32) -> Framebuffer {
+    let mut image = Framebuffer::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            image.put_pixel(
+                x,
+                y,
+                Color::new((x as f64, y as f64, 0.25 + x as f64 * 0.5)),
+            );
+        }
+    }
+    image
+}
+
+#[test]
+fn pfm_is_little_endian_and_bottom_up() {
+    let image = gradient(3, 2);
+    let bytes = write_and_read("image.pfm", |path| image.write_pfm(path).unwrap());
+    let header = b"PF\n3 2\n-1.0\n";
+    assert_eq!(&bytes[..header.len()], header);
+    let body = &bytes[header.len()..];
+    assert_eq!(body.len(), 3 * 2 * 3 * 4);
+    // the first row in the file is the bottom one
+    for (row, y) in [1, 0].iter().copied().enumerate() {
+        for x in 0..3 {
+            let at = (row * 3 + x as usize) * 12;
+            let c = image.get_pixel(x, y);
+            assert_eq!(f32_at(body, at), c.x() as f32);
+            assert_eq!(f32_at(body, at + 4), c.y() as f32);
+            assert_eq!(f32_at(body, at + 8), c.z() as f32);
+        }
+    }
+}
+
+// reads the scanlines back, run length encoded or flat
+fn decode_hdr_pixels(body: &[u8], width: usize, height: usize) -> Vec<[u8; 4]> {
+    let mut pixels = Vec::new();
+    let mut at = 0;
+    for _ in 0..height {
+        if width < 8 {
+            for pixel in body[at..at + 4 * width].chunks(4) {
+                pixels.push(pixel.try_into().unwrap());
+            }
+            at += 4 * width;
+            continue;
+        }
+        assert_eq!(body[at..at + 4], [2, 2, (width >> 8) as u8, width as u8]);
+        at += 4;
+        let mut scanline = vec![[0; 4]; width];
+        for channel in 0..4 {
+            let mut x = 0;
+            while x < width {
+                let count = body[at] as usize;
+                at += 1;
+                if count > 128 {
+                    for pixel in &mut scanline[x..x + count - 128] {
+                        pixel[channel] = body[at];
+                    }
+                    at += 1;
+                    x += count - 128;
+                } else {
+                    assert!(count > 0, "empty dump");
+                    for (pixel, &value) in scanline[x..x + count].iter_mut().zip(&body[at..]) {
+                        pixel[channel] = value;
+                    }
+                    at += count;
+                    x += count;
+                }
+            }
+            assert_eq!(x, width, "runs overshoot the scanline");
+        }
+        pixels.extend(scanline);
+    }
+    assert_eq!(at, body.len());
+    pixels
+}
+
+fn from_rgbe(rgbe: [u8; 4]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::default();
+    }
+    let scale = 2f64.powi(rgbe[3] as i32 - 136);
+    Color::new((
+        (rgbe[0] as f64 + 0.5) * scale,
+        (rgbe[1] as f64 + 0.5) * scale,
+        (rgbe[2] as f64 + 0.5) * scale,
+    ))
+}
+
+fn assert_hdr_round_trip(image: &Framebuffer, name: &str) {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let bytes = write_and_read(name, |path| image.write_hdr(path).unwrap());
+    let header = format!(
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        height, width
+    );
+    assert_eq!(&bytes[..header.len()], header.as_bytes());
+    let pixels = decode_hdr_pixels(&bytes[header.len()..], width, height);
+    for (i, &rgbe) in pixels.iter().enumerate() {
+        let expected = image.pixels()[i];
+        let decoded = from_rgbe(rgbe);
+        // 8 bits of mantissa relative to the brightest channel
+        let tolerance = expected.x().max(expected.y()).max(expected.z()) / 128.;
+        assert!(
+            (decoded - expected).len() <= 2. * tolerance,
+            "pixel {} came back as {:?} instead of {:?}",
+            i,
+            decoded,
+            expected
+        );
+    }
+}
+
+#[test]
+fn hdr_scanlines_of_8_or_more_are_run_length_encoded() {
+    // runs and literal stretches in the same scanline, and a run longer than 127
+    let mut image = Framebuffer::new(200, 3);
+    for y in 0..3 {
+        for x in 0..200 {
+            let c = match x {
+                0..=9 => Color::new((1., 0.5, 0.25)),
+                10..=19 => Color::new((x as f64, y as f64 + 1., 3.)),
+                _ => Color::new((0.1, 0.1, 0.1 * (y + 1) as f64)),
+            };
+            image.put_pixel(x, y, c);
+        }
+    }
+    assert_hdr_round_trip(&image, "runs.hdr");
+    assert_hdr_round_trip(&gradient(8, 2), "narrowest.hdr");
+}
+
+#[test]
+fn hdr_scanlines_under_8_are_flat() {
+    assert_hdr_round_trip(&gradient(5, 2), "flat.hdr");
+}
+
+// the header attributes by name, and where the header ends
+fn exr_attributes(bytes: &[u8]) -> (Vec<(String, String, Vec<u8>)>, usize) {
+    let mut attributes = Vec::new();
+    let mut at = 8;
+    let string = |at: &mut usize| {
+        let end = *at + bytes[*at..].iter().position(|&b| b == 0).unwrap();
+        let s = String::from_utf8(bytes[*at..end].to_vec()).unwrap();
+        *at = end + 1;
+        s
+    };
+    loop {
+        let name = string(&mut at);
+        if name.is_empty() {
+            return (attributes, at);
+        }
+        let kind = string(&mut at);
+        let size = i32_at(bytes, at) as usize;
+        attributes.push((name, kind, bytes[at + 4..at + 4 + size].to_vec()));
+        at += 4 + size;
+    }
+}
+
+fn exr_image() -> ExrImage {
+    let values = |base: f32| (0..6).map(|i| base + i as f32 * 0.25).collect();
+    // added out of order, the file wants them sorted
+    ExrImage::new(3, 2)
+        .with_channel("Z", values(10.))
+        .with_channel("B", values(0.))
+        .with_channel("A", values(1.))
+}
+
+#[test]
+fn exr_header_describes_the_image() {
+    let bytes = write_and_read("header.exr", |path| {
+        exr_image().write(path, ExrPixelType::Half).unwrap()
+    });
+    assert_eq!(bytes[..4], [0x76, 0x2f, 0x31, 0x01]);
+    assert_eq!(i32_at(&bytes, 4), 2);
+    let (attributes, _) = exr_attributes(&bytes);
+    let attribute = |name: &str| {
+        attributes
+            .iter()
+            .find(|a| a.0 == name)
+            .unwrap_or_else(|| panic!("no {} attribute", name))
+    };
+    let (_, kind, channels) = attribute("channels");
+    assert_eq!(kind, "chlist");
+    let mut expected = Vec::new();
+    for name in ["A", "B", "Z"] {
+        expected.extend(name.as_bytes());
+        expected.push(0);
+        // half, not linear, sampled every pixel
+        expected.extend([1, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0]);
+    }
+    expected.push(0);
+    assert_eq!(channels, &expected);
+    let window: Vec<u8> = [0i32, 0, 2, 1]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    assert_eq!(attribute("dataWindow").2, window);
+    assert_eq!(attribute("displayWindow").2, window);
+    assert_eq!(attribute("compression").2, [0]);
+    assert_eq!(attribute("lineOrder").2, [0]);
+}
+
+#[test]
+fn exr_scanlines_hold_the_channels_in_order() {
+    let image = exr_image();
+    for (pixel_type, size) in [(ExrPixelType::Float, 4), (ExrPixelType::Half, 2)] {
+        let bytes = write_and_read("lines.exr", |path| image.write(path, pixel_type).unwrap());
+        let (_, header_end) = exr_attributes(&bytes);
+        let line_size = 3 * 3 * size;
+        for y in 0..2 {
+            let at = header_end + y * 8;
+            let offset = u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap()) as usize;
+            assert_eq!(offset, header_end + 2 * 8 + y * (8 + line_size));
+            assert_eq!(i32_at(&bytes, offset), y as i32);
+            assert_eq!(i32_at(&bytes, offset + 4), line_size as i32);
+            for (c, base) in [1f32, 0., 10.].iter().copied().enumerate() {
+                for x in 0..3 {
+                    let value = base + (y * 3 + x) as f32 * 0.25;
+                    let at = offset + 8 + (c * 3 + x) * size;
+                    match pixel_type {
+                        ExrPixelType::Float => assert_eq!(f32_at(&bytes, at), value),
+                        ExrPixelType::Half => assert_eq!(
+                            u16::from_le_bytes([bytes[at], bytes[at + 1]]),
+                            f32_to_half(value)
+                        ),
+                    }
+                }
+            }
+        }
+        assert_eq!(bytes.len(), header_end + 2 * 8 + 2 * (8 + line_size));
+    }
+}
+
+#[test]
+fn half_conversion_rounds_to_nearest_even() {
+    let cases: [(f32, u16); 18] = [
+        (0., 0x0000),
+        (-0., 0x8000),
+        (1., 0x3c00),
+        (-2., 0xc000),
+        (0.5, 0x3800),
+        (65504., 0x7bff),
+        // halfway between the largest half and the next step rounds to even, which
+        // overflows into infinity
+        (65520., 0x7c00),
+        (1e10, 0x7c00),
+        (f32::INFINITY, 0x7c00),
+        (f32::NEG_INFINITY, 0xfc00),
+        // ties between 1 and its neighbors go to the even mantissa
+        (1. + 1. / 2048., 0x3c00),
+        (1. + 3. / 2048., 0x3c02),
+        // the smallest normal and the denormals below it
+        (2f32.powi(-14), 0x0400),
+        (2f32.powi(-24), 0x0001),
+        (2f32.powi(-25), 0x0000),
+        (3. * 2f32.powi(-25), 0x0002),
+        (2f32.powi(-25) * (1. + f32::EPSILON), 0x0001),
+        // the largest denormal rounding up into the smallest normal
+        (6.103e-5, 0x0400),
+    ];
+    for (value, half) in cases {
+        assert_eq!(f32_to_half(value), half, "{:e}", value);
+    }
+    let nan = f32_to_half(f32::NAN);
+    assert_eq!(nan & 0x7c00, 0x7c00);
+    assert_ne!(nan & 0x3ff, 0);
+}
@@ -0,0 +1,177 @@
+use std::{f64::consts::PI, path::PathBuf, sync::Arc};
+
+use ray_tracing_the_next_week::{
+    load_ply, Color, HitRecord, Hittable, Lambertian, LoadError, Material, Point3, Ray, Texture,
+    TriangleMesh, Vec3, AABB, INFINITY,
+};
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+fn white() -> Arc<dyn Material> {
+    Arc::new(Lambertian::new(Color::new((0.73, 0.73, 0.73))))
+}
+
+fn bounding_box(object: &dyn Hittable) -> AABB {
+    let mut output_box = AABB::default();
+    assert!(object.bounding_box((0., 1.), &mut output_box));
+    output_box
+}
+
+#[test]
+fn ascii_square_is_split_into_two_triangles() {
+    let mesh = load_ply(fixture("square_ascii.ply"), white()).unwrap();
+    assert_eq!(mesh.triangle_count(), 2);
+    assert_eq!(mesh.vertex_count(), 4);
+    let r#box = bounding_box(&mesh);
+    assert!((r#box.min().x() - 0.).abs() < 1e-3 && (r#box.max().y() - 1.).abs() < 1e-3);
+
+    let down = Ray::new(Point3::new((0.25, 0.75, 1.)), Vec3::new((0., 0., -1.)), 0.);
+    assert!(mesh.hit(&down, 0.001, INFINITY).is_some());
+    let outside = Ray::new(Point3::new((1.5, 0.5, 1.)), Vec3::new((0., 0., -1.)), 0.);
+    assert!(mesh.hit(&outside, 0.001, INFINITY).is_none());
+}
+
+#[test]
+fn binary_square_matches_ascii_square() {
+    let ascii = load_ply(fixture("square_ascii.ply"), white()).unwrap();
+    let binary = load_ply(fixture("square_binary.ply"), white()).unwrap();
+    assert_eq!(binary.triangle_count(), ascii.triangle_count());
+    assert_eq!(binary.vertex_count(), ascii.vertex_count());
+    let (a, b) = (bounding_box(&ascii), bounding_box(&binary));
+    assert_eq!(a.min(), b.min());
+    assert_eq!(a.max(), b.max());
+    for i in 0..10 {
+        let x = -0.05 + 0.12 * i as f64;
+        let ray = Ray::new(Point3::new((x, 0.5, 1.)), Vec3::new((0., 0., -1.)), 0.);
+        assert_eq!(
+            ascii.hit(&ray, 0.001, INFINITY).is_some(),
+            binary.hit(&ray, 0.001, INFINITY).is_some()
+        );
+    }
+}
+
+#[test]
+fn binary_pyramid_without_normals_or_uvs() {
+    let mesh = load_ply(fixture("pyramid_binary.ply"), white()).unwrap();
+    // four sides plus a base quad split in two
+    assert_eq!(mesh.triangle_count(), 6);
+    assert_eq!(mesh.vertex_count(), 5);
+    let from_above = Ray::new(Point3::new((0.5, 3., 0.5)), Vec3::new((0., -1., 0.)), 0.);
+    assert!(mesh.hit(&from_above, 0.001, INFINITY).is_some());
+}
+
+#[test]
+fn truncated_binary_body_is_an_error() {
+    match load_ply(fixture("truncated_binary.ply"), white()) {
+        Err(LoadError::Malformed { .. }) => {}
+        other => panic!("expected a malformed error, got {:?}", other.err()),
+    }
+}
+
+#[test]
+fn bad_ascii_value_reports_its_line() {
+    match load_ply(fixture("bad_value_ascii.ply"), white()) {
+        Err(LoadError::Parse { line, .. }) => assert_eq!(line, 13),
+        other => panic!("expected a parse error, got {:?}", other.err()),
+    }
+}
+
+#[test]
+fn missing_file_is_an_io_error() {
+    match load_ply(fixture("does_not_exist.ply"), white()) {
+        Err(LoadError::Io { .. }) => {}
+        other => panic!("expected an io error, got {:?}", other.err()),
+    }
+}
+
+#[test]
+fn negative_face_index_reports_its_line() {
+    match load_ply(fixture("negative_index_ascii.ply"), white()) {
+        Err(LoadError::Parse { line, .. }) => assert_eq!(line, 14),
+        other => panic!("expected a parse error, got {:?}", other.err()),
+    }
+}
+
+#[test]
+fn fractional_face_index_is_an_error() {
+    match load_ply(fixture("fractional_index_ascii.ply"), white()) {
+        Err(LoadError::Parse { line, .. }) => assert_eq!(line, 13),
+        other => panic!("expected a parse error, got {:?}", other.err()),
+    }
+}
+
+// shows the texture coordinates as the red and green of the albedo
+struct UvTexture;
+
+impl Texture for UvTexture {
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        Color::new((u, v, 0.))
+    }
+}
+
+fn uv_material() -> Arc<dyn Material> {
+    Arc::new(Lambertian::with_texture(Arc::new(UvTexture)))
+}
+
+// where a ray straight down onto (x, y) hits the mesh
+fn hit_at(mesh: &TriangleMesh, x: f64, y: f64) -> HitRecord {
+    let down = Ray::new(Point3::new((x, y, 1.)), Vec3::new((0., 0., -1.)), 0.);
+    mesh.hit(&down, 0.001, INFINITY)
+        .expect("ray misses the mesh")
+}
+
+// the lambertian pdf of light from straight above is the cosine to the shading normal
+fn cosine_to_up(mesh: &TriangleMesh, rec: &HitRecord) -> f64 {
+    let up = Vec3::new((0., 0., 1.));
+    mesh.material().pdf(rec, &up, &up) * PI
+}
+
+#[test]
+fn vertex_normals_and_uvs_are_interpolated() {
+    let mesh = load_ply(fixture("bent_square_ascii.ply"), uv_material()).unwrap();
+    for &(x, y) in &[(0.1, 0.2), (0.5, 0.5), (0.9, 0.3), (0.25, 0.8)] {
+        let rec = hit_at(&mesh, x, y);
+        // the normal at x is (x, 0, 1) normalized
+        let expected = 1. / (1. + x * x).sqrt();
+        assert!((cosine_to_up(&mesh, &rec) - expected).abs() < 1e-6);
+        let uv = mesh.material().albedo(&rec);
+        assert!(
+            (uv.x() - (1. - x)).abs() < 1e-6,
+            "u at {} {} is {}",
+            x,
+            y,
+            uv.x()
+        );
+        assert!(
+            (uv.y() - (1. - y)).abs() < 1e-6,
+            "v at {} {} is {}",
+            x,
+            y,
+            uv.y()
+        );
+    }
+}
+
+#[test]
+fn attributes_missing_from_some_vertices_are_dropped() {
+    let mesh = load_ply(fixture("partial_attributes_ascii.ply"), uv_material()).unwrap();
+    let positions = vec![
+        Point3::new((0., 0., 0.)),
+        Point3::new((1., 0., 0.)),
+        Point3::new((1., 1., 0.)),
+        Point3::new((0., 1., 0.)),
+    ];
+    let bare = TriangleMesh::new(positions, vec![[0, 1, 2], [0, 2, 3]], uv_material());
+    for &(x, y) in &[(0.1, 0.2), (0.5, 0.5), (0.9, 0.3), (0.25, 0.8)] {
+        let rec = hit_at(&mesh, x, y);
+        // flat shaded, so the normal is the face's own
+        assert!((cosine_to_up(&mesh, &rec) - 1.).abs() < 1e-9);
+        let uv = mesh.material().albedo(&rec);
+        let bare_uv = bare.material().albedo(&hit_at(&bare, x, y));
+        assert!((uv - bare_uv).len() < 1e-9, "{:?} is not {:?}", uv, bare_uv);
+    }
+}
@@ -0,0 +1,160 @@
+use std::{path::PathBuf, sync::Arc};
+
+use ray_tracing_the_next_week::{
+    Camera, CheckpointError, Color, ConstantMedium, DiffuseLight, Framebuffer, HittableList,
+    Lambertian, Metal, Point3, RenderSettings, Renderer, Sphere, Vec3, XZPlane,
+};
+
+// a little of everything that draws samples: a light to sample, rough metal, fog and
+// a lens
+fn scene(settings: RenderSettings) -> Renderer {
+    scene_with_floor(settings, Color::new((0.73, 0.73, 0.73)))
+}
+
+fn scene_with_floor(settings: RenderSettings, floor: Color) -> Renderer {
+    let white = Arc::new(Lambertian::new(Color::new((0.73, 0.73, 0.73))));
+    let floor = Arc::new(Lambertian::new(floor));
+    let metal = Arc::new(Metal::new(Color::new((0.8, 0.6, 0.2)), 0.3));
+    let light = Arc::new(DiffuseLight::with_solid_color(Color::new((6., 6., 6.))));
+    let light = Arc::new(XZPlane::new(light, (-1., 1.), (-1., 1.), 4.));
+    let mut world = HittableList::new();
+    world.add(Arc::new(XZPlane::new(floor, (-20., 20.), (-20., 20.), 0.)));
+    world.add(Arc::new(Sphere::new(Point3::new((-1., 1., 0.)), 1., metal)));
+    let fog = Arc::new(Sphere::new(Point3::new((1.2, 0.8, 0.)), 0.8, white));
+    world.add(Arc::new(ConstantMedium::from_color(
+        fog,
+        Color::new((0.9, 0.9, 0.9)),
+        1.5,
+    )));
+    world.add(light.clone());
+    let camera = Camera::new(
+        Point3::new((0., 2., -7.)),
+        Point3::new((0., 1., 0.)),
+        Vec3::new((0., 1., 0.)),
+        40.,
+        1.,
+        0.1,
+        7.,
+        0.,
+        1.,
+    );
+    Renderer::new(
+        camera,
+        Arc::new(world),
+        Color::new((0.1, 0.1, 0.2)),
+        settings,
+    )
+    .with_lights(light)
+}
+
+fn settings() -> RenderSettings {
+    RenderSettings {
+        image_width: 20,
+        image_height: 16,
+        samples_per_pixel: 16,
+        max_depth: 6,
+        seed: 5,
+        ..Default::default()
+    }
+}
+
+fn assert_same_image(a: &Framebuffer, b: &Framebuffer) {
+    assert_eq!((a.width(), a.height()), (b.width(), b.height()));
+    for (i, (p, q)) in a.pixels().iter().zip(b.pixels()).enumerate() {
+        assert!(
+            p.x().to_bits() == q.x().to_bits()
+                && p.y().to_bits() == q.y().to_bits()
+                && p.z().to_bits() == q.z().to_bits(),
+            "pixel {} is {:?} in one and {:?} in the other",
+            i,
+            p,
+            q
+        );
+    }
+}
+
+#[test]
+fn thread_and_tile_counts_do_not_change_the_image() {
+    let reference = scene(RenderSettings {
+        threads: 1,
+        tile_size: 16,
+        ..settings()
+    })
+    .render();
+    for (threads, tile_size) in [(2, 16), (3, 5), (4, 1), (1, 64)] {
+        let image = scene(RenderSettings {
+            threads,
+            tile_size,
+            ..settings()
+        })
+        .render();
+        assert_same_image(&reference, &image);
+    }
+}
+
+#[test]
+fn seed_changes_the_image() {
+    let a = scene(settings()).render();
+    let b = scene(RenderSettings {
+        seed: 6,
+        ..settings()
+    })
+    .render();
+    assert!(a.pixels().iter().zip(b.pixels()).any(|(p, q)| p != q));
+}
+
+#[test]
+fn progressive_render_ends_up_with_the_same_image() {
+    let renderer = scene(settings());
+    let reference = renderer.render();
+    for samples_per_pass in [1, 5, 16] {
+        let mut passes = 0;
+        let accumulator = renderer.render_progressive(samples_per_pass, |_, _| passes += 1);
+        assert_eq!(passes, 16usize.div_ceil(samples_per_pass));
+        assert_same_image(&reference, &accumulator.to_framebuffer());
+    }
+}
+
+// a file in the temp directory, unique to the test writing it
+fn scratch(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("render-test-{}-{}", std::process::id(), name))
+}
+
+#[test]
+fn resuming_a_checkpoint_ends_up_with_the_same_image() {
+    let renderer = scene(settings());
+    let path = scratch("resume.ckpt");
+    let mut accumulator = renderer.accumulator();
+    renderer.render_pass(&mut accumulator, 6);
+    renderer.save_checkpoint(&accumulator, &path).unwrap();
+    let loaded = renderer.load_checkpoint(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let resumed = renderer.resume_progressive(loaded, 4, |_, _| {});
+    assert_same_image(&renderer.render(), &resumed.to_framebuffer());
+}
+
+fn assert_mismatch(saved_by: &Renderer, loaded_by: &Renderer, name: &str) {
+    let path = scratch(name);
+    let mut accumulator = saved_by.accumulator();
+    saved_by.render_pass(&mut accumulator, 1);
+    saved_by.save_checkpoint(&accumulator, &path).unwrap();
+    let loaded = loaded_by.load_checkpoint(&path);
+    std::fs::remove_file(&path).unwrap();
+    match loaded {
+        Err(CheckpointError::Mismatch { .. }) => {}
+        other => panic!("expected a mismatch, got {:?}", other.err()),
+    }
+}
+
+#[test]
+fn checkpoint_of_a_scene_with_another_material_is_refused() {
+    let red_floor = scene_with_floor(settings(), Color::new((0.73, 0.2, 0.2)));
+    assert_mismatch(&scene(settings()), &red_floor, "material.ckpt");
+}
+
+#[test]
+fn checkpoint_with_another_scene_key_is_refused() {
+    let first = scene(settings()).with_scene_key(1);
+    let second = scene(settings()).with_scene_key(2);
+    assert_mismatch(&first, &second, "key.ckpt");
+}
@@ -0,0 +1,72 @@
+use ray_tracing_the_next_week::{srgb_encode, Color, ToneMap, ToneMapOperator};
+
+fn operators() -> [ToneMapOperator; 5] {
+    [
+        ToneMapOperator::Clamp,
+        ToneMapOperator::Reinhard,
+        ToneMapOperator::ExtendedReinhard { white: 4. },
+        ToneMapOperator::Hable,
+        ToneMapOperator::Aces,
+    ]
+}
+
+#[test]
+fn srgb_encode_is_continuous_where_the_pieces_meet() {
+    let knee = 0.0031308;
+    let below = srgb_encode(knee * (1. - 1e-9));
+    let above = srgb_encode(knee * (1. + 1e-9));
+    assert!(
+        (above - below).abs() < 1e-7,
+        "{} below, {} above",
+        below,
+        above
+    );
+    assert_eq!(srgb_encode(0.), 0.);
+    assert!((srgb_encode(1.) - 1.).abs() < 1e-12);
+}
+
+#[test]
+fn every_operator_maps_black_to_black() {
+    for operator in operators() {
+        let mapped = ToneMap::new(operator).apply(&Color::default());
+        assert_eq!(mapped.len(), 0., "{:?}", operator);
+    }
+}
+
+#[test]
+fn every_operator_is_monotone() {
+    for operator in operators() {
+        let tone_map = ToneMap::new(operator);
+        let mut previous = 0.;
+        for i in 1..=2000 {
+            // grays from very dark up to 100
+            let value = 1e-4 * 1.0069f64.powi(i);
+            let mapped = tone_map.apply(&Color::new((value, value, value)));
+            assert!(
+                mapped.y() >= previous,
+                "{:?} maps {} to {}, below {}",
+                operator,
+                value,
+                mapped.y(),
+                previous
+            );
+            previous = mapped.y();
+        }
+    }
+}
+
+#[test]
+fn extended_reinhard_reaches_white_at_white() {
+    for white in [1., 2.5, 11.2] {
+        let tone_map = ToneMap::new(ToneMapOperator::ExtendedReinhard { white });
+        let mapped = tone_map.apply(&Color::new((white, white, white)));
+        for channel in [mapped.x(), mapped.y(), mapped.z()] {
+            assert!(
+                (channel - 1.).abs() < 1e-12,
+                "{} maps to {:?}",
+                white,
+                mapped
+            );
+        }
+    }
+}